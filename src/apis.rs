@@ -1,15 +1,26 @@
-use std::mem;
+use std::{pin::pin, time::Duration};
 
 use async_stream::try_stream;
 use chrono::NaiveDate;
 use deranged::{OptionRangedU64, RangedU64};
 use derive_is_enum_variant::is_enum_variant;
-use futures::Stream;
-use serde::{Deserialize, Deserializer};
+use futures::{Stream, StreamExt, stream};
+use itertools::Itertools;
+use reqwest::{
+    StatusCode,
+    header::{HeaderMap, HeaderName},
+};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use thiserror::Error;
 
-use crate::private::RobloxErrorSealed;
+use crate::{
+    challenge::{CHALLENGE_ID_HEADER, CHALLENGE_METADATA_HEADER, CHALLENGE_TYPE_HEADER, Challenge},
+    private::RobloxErrorSealed,
+    retry::parse_retry_after,
+};
+
+use pagination::PageDirection;
 
 pub use chrono;
 pub use futures;
@@ -19,7 +30,10 @@ pub mod economy;
 pub mod games;
 pub mod general;
 pub mod groups;
+pub mod open_cloud;
+pub mod pagination;
 pub mod thumbnails;
+pub mod two_step_verification;
 pub mod users;
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
@@ -93,12 +107,17 @@ fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveD
 
 pub type RequestResult<T, E> = Result<T, Error<E>>;
 
-#[derive(Deserialize, Default, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy)]
 #[serde(deny_unknown_fields)]
 pub struct Empty {}
 
 pub trait RobloxError: RobloxErrorSealed + std::error::Error + Send {
     fn parse(res: String) -> Self;
+    /// Whether the body was parsed into the error structure of the api, rather than kept as is
+    #[inline]
+    fn is_structured(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Default, Error)]
@@ -113,44 +132,214 @@ impl RobloxError for StringError {
     }
 }
 
-#[derive(Debug, Default, Error, is_enum_variant)]
+/// Message of the 403 answered when the session is invalid, rather than lacking permission
+const AUTHORIZATION_DENIED: &str = "Authorization has been denied for this request.";
+
+/// Response headers kept in [`ResponseInfo`]
+const KEPT_HEADERS: [&str; 10] = [
+    "content-type",
+    "retry-after",
+    "x-csrf-token",
+    "x-ratelimit-limit",
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+    "www-authenticate",
+    CHALLENGE_ID_HEADER,
+    CHALLENGE_TYPE_HEADER,
+    CHALLENGE_METADATA_HEADER,
+];
+
+/// Status, selected headers and body of a response that could not be turned into a value
+#[derive(Debug, Clone, Default)]
+pub struct ResponseInfo {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+impl ResponseInfo {
+    pub(crate) fn new(status: StatusCode, headers: &HeaderMap, body: String) -> Self {
+        let headers = KEPT_HEADERS
+            .iter()
+            .flat_map(|name| {
+                headers
+                    .get_all(*name)
+                    .iter()
+                    .map(|value| (HeaderName::from_static(name), value.clone()))
+            })
+            .collect();
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+}
+
+#[derive(Debug, Error, is_enum_variant)]
 #[non_exhaustive]
 pub enum Error<T: RobloxError> {
-    #[error(transparent)]
-    Api(#[from] T),
+    /// Error returned by the api, along with the response when it came from one
+    #[error("{error}")]
+    Api {
+        error: T,
+        response: Option<Box<ResponseInfo>>,
+    },
 
     #[error("request error: {0}")]
     Request(#[from] reqwest::Error),
 
     #[error("rate limited")]
-    #[default]
-    RateLimit,
+    RateLimit(Option<Box<ResponseInfo>>),
+
+    /// A 401 whose body is not an error of the api, which is returned as [`Self::Api`] otherwise
+    #[error("unauthorized")]
+    Unauthorized(Option<Box<ResponseInfo>>),
+
+    /// The action needs a challenge solved first, see [`ChallengeSolver`](crate::challenge::ChallengeSolver)
+    #[error("challenge required: {challenge}")]
+    ChallengeRequired {
+        challenge: Box<Challenge>,
+        error: T,
+        response: Box<ResponseInfo>,
+    },
+
+    /// Successful response whose body is neither the expected type nor an error of the api
+    #[error("failed to decode response at `{path}`: {message}")]
+    Decode {
+        path: String,
+        message: String,
+        response: Box<ResponseInfo>,
+    },
+}
+impl<T: RobloxError> From<T> for Error<T> {
+    fn from(error: T) -> Self {
+        Self::Api {
+            error,
+            response: None,
+        }
+    }
+}
+impl<T: RobloxError> Error<T> {
+    #[must_use]
+    pub fn response(&self) -> Option<&ResponseInfo> {
+        match self {
+            Self::Api { response, .. }
+            | Self::RateLimit(response)
+            | Self::Unauthorized(response) => response.as_deref(),
+            Self::ChallengeRequired { response, .. } | Self::Decode { response, .. } => {
+                Some(response)
+            }
+            Self::Request(_) => None,
+        }
+    }
+    #[must_use]
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Request(error) => error.status(),
+            _ => self.response().map(|response| response.status),
+        }
+    }
+    /// Whether sending the same request again later may succeed
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimit(_) => true,
+            Self::Request(error) => error.is_connect() || error.is_timeout(),
+            _ => self.status().is_some_and(|status| status.is_server_error()),
+        }
+    }
+    /// Whether the credentials were rejected or lack access (401 or 403 without a challenge)
+    #[must_use]
+    pub fn is_auth_failure(&self) -> bool {
+        self.is_unauthorized()
+            || !self.is_challenge_required()
+                && self.status().is_some_and(|status| {
+                    status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN
+                })
+    }
+    /// Whether the session itself was rejected: a 401, or a 403 denying authorization,
+    /// as opposed to a 403 for lacking the permission to do this one thing
+    #[must_use]
+    pub fn is_session_invalid(&self) -> bool {
+        match self.status() {
+            Some(StatusCode::UNAUTHORIZED) => true,
+            Some(StatusCode::FORBIDDEN) => {
+                !self.is_challenge_required()
+                    && self.response().is_some_and(|response| {
+                        JsonError::parse(response.body.clone())
+                            .errors()
+                            .iter()
+                            .any(|error| error.code == 0 && error.message == AUTHORIZATION_DENIED)
+                    })
+            }
+            _ => self.is_unauthorized(),
+        }
+    }
+    /// Delay asked for by the `Retry-After` header
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.response()
+            .and_then(|response| parse_retry_after(&response.headers))
+    }
 }
 
+/// Declares a service's error codes, with an `Unknown` fallback for undocumented ones
+macro_rules! error_codes {
+    ($(#[$meta: meta])* $name: ident { $($(#[$variant_meta: meta])* $variant: ident = $code: literal,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            Unknown(i32),
+        }
+        impl From<i32> for $name {
+            fn from(code: i32) -> Self {
+                match code {
+                    $($code => Self::$variant,)*
+                    code => Self::Unknown(code),
+                }
+            }
+        }
+        impl From<$name> for i32 {
+            fn from(code: $name) -> Self {
+                match code {
+                    $($name::$variant => $code,)*
+                    $name::Unknown(code) => code,
+                }
+            }
+        }
+    };
+}
+pub(crate) use error_codes;
+
 #[derive(Debug, Deserialize, Clone, Default)]
 struct JsonErrors {
-    errors: [InnerJsonError; 1],
+    errors: Vec<JsonErrorEntry>,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+/// One entry of the `errors` list returned by the web apis
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-struct InnerJsonError {
-    pub code: i8,
+pub struct JsonErrorEntry {
+    pub code: i32,
     pub message: String,
     pub user_facing_message: Option<String>,
     pub field: Option<String>,
 }
+impl JsonErrorEntry {
+    /// The code as one of the per service enums, like [`groups::GroupsErrorCode`]
+    #[must_use]
+    pub fn code_as<C: From<i32>>(&self) -> C {
+        C::from(self.code)
+    }
+}
 
 #[derive(Debug, Deserialize, Error, Clone)]
 #[serde(rename_all = "camelCase", from = "JsonErrors")]
 #[error("{}", self.display_error_message())]
 pub enum JsonError {
-    Valid {
-        code: i8,
-        message: String,
-        user_facing_message: Option<String>,
-        field: Option<String>,
-    },
+    Valid { errors: Vec<JsonErrorEntry> },
     Malformed(String),
 }
 impl RobloxErrorSealed for JsonError {}
@@ -158,19 +347,23 @@ impl RobloxError for JsonError {
     fn parse(res: String) -> Self {
         sonic_rs::from_str::<Self>(&res).map_or(Self::Malformed(res), |value| value)
     }
+    #[inline]
+    fn is_structured(&self) -> bool {
+        !matches!(self, Self::Malformed(_))
+    }
 }
 impl JsonError {
     #[must_use]
     pub fn display_error_message(&self) -> String {
         match self {
-            Self::Valid {
-                user_facing_message,
-                message,
-                ..
-            } => {
+            Self::Valid { errors } if errors.is_empty() => String::from("json error: no details"),
+            Self::Valid { errors } => {
                 format!(
                     "json error: {}",
-                    user_facing_message.as_ref().unwrap_or(message)
+                    errors
+                        .iter()
+                        .map(|error| error.user_facing_message.as_ref().unwrap_or(&error.message))
+                        .join("; ")
                 )
             }
             Self::Malformed(value) => {
@@ -178,14 +371,34 @@ impl JsonError {
             }
         }
     }
+    #[must_use]
+    pub fn errors(&self) -> &[JsonErrorEntry] {
+        match self {
+            Self::Valid { errors } => errors,
+            Self::Malformed(_) => &[],
+        }
+    }
+    /// Code of the first error
+    #[must_use]
+    pub fn code(&self) -> Option<i32> {
+        self.errors().first().map(|error| error.code)
+    }
+    /// Code of the first error as one of the per service enums, like [`groups::GroupsErrorCode`]
+    #[must_use]
+    pub fn code_as<C: From<i32>>(&self) -> Option<C> {
+        self.code().map(C::from)
+    }
+    /// Whether any of the errors has the code
+    #[must_use]
+    pub fn has_code<C: Into<i32>>(&self, code: C) -> bool {
+        let code = code.into();
+        self.errors().iter().any(|error| error.code == code)
+    }
 }
 impl From<JsonErrors> for JsonError {
-    fn from(mut value: JsonErrors) -> Self {
+    fn from(value: JsonErrors) -> Self {
         Self::Valid {
-            code: value.errors[0].code,
-            message: mem::take(&mut value.errors[0].message),
-            user_facing_message: mem::take(&mut value.errors[0].user_facing_message),
-            field: mem::take(&mut value.errors[0].field),
+            errors: value.errors,
         }
     }
 }
@@ -220,8 +433,21 @@ pub struct Page<T> {
 }
 
 pub fn paginate<T, R, E>(
+    request: R,
+    cursor: Option<impl Into<String>>,
+) -> impl Stream<Item = RequestResult<Page<T>, E>>
+where
+    R: AsyncFnMut(Option<&'_ str>) -> RequestResult<Page<T>, E>,
+    E: RobloxError,
+{
+    paginate_in(request, cursor, PageDirection::Forward)
+}
+
+/// Like [`paginate`], following the previous page cursors when walking backward
+pub fn paginate_in<T, R, E>(
     mut request: R,
     cursor: Option<impl Into<String>>,
+    direction: PageDirection,
 ) -> impl Stream<Item = RequestResult<Page<T>, E>>
 where
     R: AsyncFnMut(Option<&'_ str>) -> RequestResult<Page<T>, E>,
@@ -231,12 +457,104 @@ where
         let mut cursor: Option<String> = cursor.map(Into::into);
         loop {
             let response = request(cursor.as_deref()).await?;
-            if response.next_page_cursor.is_none() {
+            let Some(next_cursor) = direction.cursor(&response) else {
                 yield response;
                 break;
-            }
-            cursor.clone_from(&response.next_page_cursor);
+            };
+            cursor = Some(String::from(next_cursor));
             yield response;
         }
     }
 }
+
+/// One request of a batch split into chunks, with the inputs it was made for
+#[derive(Debug)]
+pub struct BatchChunk<I, T, E: RobloxError> {
+    pub inputs: Vec<I>,
+    pub result: RequestResult<Vec<T>, E>,
+}
+
+/// Splits the inputs into chunks of at most `chunk_size`, requesting up to `concurrency` at a time
+///
+/// Chunks are yielded in the order of the inputs.
+pub fn batch_chunks<I, T, E, R, F>(
+    inputs: impl IntoIterator<Item = I>,
+    chunk_size: usize,
+    concurrency: usize,
+    request: R,
+) -> impl Stream<Item = BatchChunk<I, T, E>>
+where
+    I: Clone,
+    E: RobloxError,
+    R: Fn(Vec<I>) -> F,
+    F: Future<Output = RequestResult<Vec<T>, E>>,
+{
+    let chunks = inputs
+        .into_iter()
+        .chunks(chunk_size.max(1))
+        .into_iter()
+        .map(Iterator::collect)
+        .collect::<Vec<Vec<I>>>();
+    stream::iter(chunks)
+        .map(move |inputs| {
+            let result = request(inputs.clone());
+            async move {
+                BatchChunk {
+                    inputs,
+                    result: result.await,
+                }
+            }
+        })
+        .buffered(concurrency.max(1))
+}
+
+/// Merges the chunks in order, failing with the first chunk that failed
+pub async fn merge_batch_chunks<I, T, E: RobloxError>(
+    chunks: impl Stream<Item = BatchChunk<I, T, E>>,
+) -> RequestResult<Vec<T>, E> {
+    let mut chunks = pin!(chunks);
+    let mut merged = Vec::new();
+    while let Some(chunk) = chunks.next().await {
+        merged.extend(chunk.result?);
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonError, RobloxError, groups::GroupsErrorCode};
+
+    #[test]
+    fn json_error_keeps_every_entry() {
+        let error = JsonError::parse(String::from(
+            r#"{"errors":[{"code":1,"message":"The group is invalid or does not exist."},{"code":3,"message":"The user is invalid or does not exist.","userFacingMessage":"Something went wrong","field":"userId"}]}"#,
+        ));
+        let errors = error.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].code, 3);
+        assert_eq!(
+            errors[1].user_facing_message.as_deref(),
+            Some("Something went wrong")
+        );
+        assert_eq!(errors[1].field.as_deref(), Some("userId"));
+        assert_eq!(error.code_as(), Some(GroupsErrorCode::InvalidGroup));
+        assert!(error.has_code(GroupsErrorCode::InvalidUser));
+        assert!(!error.has_code(GroupsErrorCode::InvalidRole));
+        assert_eq!(
+            error.to_string(),
+            "json error: The group is invalid or does not exist.; Something went wrong"
+        );
+    }
+
+    #[test]
+    fn unknown_codes_round_trip() {
+        let error = JsonError::parse(String::from(
+            r#"{"errors":[{"code":4242,"message":"new"}]}"#,
+        ));
+        let code = error.code_as::<GroupsErrorCode>().unwrap();
+        assert_eq!(code, GroupsErrorCode::Unknown(4242));
+        assert_eq!(i32::from(code), 4242);
+        assert_eq!(GroupsErrorCode::from(9), GroupsErrorCode::GroupLocked);
+        assert_eq!(i32::from(GroupsErrorCode::GroupLocked), 9);
+    }
+}
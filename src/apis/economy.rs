@@ -1,10 +1,24 @@
 use async_trait::async_trait;
 use serde::Deserialize;
 
-use crate::{AuthenticatedClient, RequestResult};
+use crate::{AuthenticatedClient, RequestResult, endpoints::Service};
 
 use super::{Id, JsonError};
 
+super::error_codes! {
+    /// Codes of [`JsonError`]s returned by the economy api
+    EconomyErrorCode {
+        /// The group is invalid or does not exist
+        InvalidGroup = 1,
+        /// You don't have permission to view this group's currency
+        InsufficientPermissions = 2,
+        /// The user is invalid or does not exist
+        InvalidUser = 3,
+        /// The transaction type is invalid
+        InvalidTransactionType = 4,
+    }
+}
+
 #[derive(Deserialize, Debug, Default, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 struct Robux {
@@ -12,11 +26,12 @@ struct Robux {
 }
 
 macro_rules! add_base_url {
-    ($api_route: literal) => {
-        concat!("https://economy.roblox.com/", $api_route)
-    };
-    ($api_format_string: expr, $($args:expr),+) => {
-        format!(concat!("https://economy.roblox.com/", $api_format_string), $($args),+)
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::Economy)
+            $(, $args)*
+        )
     };
 }
 
@@ -25,7 +40,11 @@ pub trait EconomyAuthenticatedApi: AuthenticatedClient {
     async fn get_group_funds(&self, group: Id) -> RequestResult<u64, JsonError> {
         let response = self
             .authenticated_get::<Robux, _>(
-                add_base_url!("v1/groups/{}/currency", group),
+                add_base_url!(
+                    self.authenticated_endpoints(),
+                    "v1/groups/{}/currency",
+                    group
+                ),
                 None::<()>,
             )
             .await?;
@@ -1,15 +1,47 @@
 use crate::{
     BaseClient,
-    apis::{RequestLimit, SortOrder},
+    apis::{
+        RequestLimit, SortOrder,
+        thumbnails::{
+            BatchRequest, BatchThumbnailResultExt, ThumbnailSize, ThumbnailType, ThumbnailsApi,
+        },
+    },
+    endpoints::Service,
+    rate_limit::Quota,
 };
 use async_trait::async_trait;
 use chrono::NaiveDate;
 use futures::Stream;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use uuid::Uuid;
 
 use super::{Id, JsonError, Page, RequestResult, SortOrderDefaultDescending, StringError};
 
+/// Rate limit of [`GamesApi::get_public_servers_manual`]
+pub const PUBLIC_SERVERS_QUOTA: Quota =
+    Quota::new("games/v1/games/servers", 10, Duration::from_millis(3500));
+
+/// How long [`GamesApi::find_user_in_servers`] waits for pending headshots
+const HEADSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+super::error_codes! {
+    /// Codes of [`JsonError`]s returned by the games api
+    GamesErrorCode {
+        /// The place is invalid
+        InvalidPlace = 1,
+        /// The requested universe does not exist
+        InvalidUniverse = 2,
+        /// The universe's root place is invalid
+        InvalidRootPlace = 3,
+        /// No universe ids were specified
+        NoUniverseIds = 8,
+        /// Too many universe ids were requested
+        TooManyUniverseIds = 9,
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub enum ServerType {
     #[default]
@@ -39,11 +71,12 @@ pub struct ServerListPlayer {
 }
 
 macro_rules! add_base_url {
-    ($api_route: literal) => {
-        concat!("https://games.roblox.com/", $api_route)
-    };
-    ($api_format_string: expr, $($args:expr),+) => {
-        format!(concat!("https://games.roblox.com/", $api_format_string), $($args),+)
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::Games)
+            $(, $args)*
+        )
     };
 }
 
@@ -116,7 +149,10 @@ pub trait GamesApi: BaseClient {
     /// Very large or no rate limit
     async fn get_place_details(&self, place_id: Id) -> RequestResult<PlaceDetails, StringError> {
         self.get(
-            "https://www.roblox.com/places/api-get-details",
+            format!(
+                "{}places/api-get-details",
+                self.endpoints().get(Service::Www)
+            ),
             Some([("assetId", place_id)]),
         )
         .await
@@ -156,17 +192,117 @@ pub trait GamesApi: BaseClient {
         limit: RequestLimit,
         cursor: Option<impl Serialize + Send>,
     ) -> RequestResult<Page<PublicServer>, JsonError> {
-        self.get(
-            add_base_url!("v1/games/{}/servers/{}", place_id, server_type as u8),
+        self.request_with_quota(
+            PUBLIC_SERVERS_QUOTA,
+            Method::GET,
+            add_base_url!(
+                self.endpoints(),
+                "v1/games/{}/servers/{}",
+                place_id,
+                server_type as u8
+            ),
             Some(BatchParameters {
                 sort_order: sort_order.into(),
                 exclude_full_servers,
                 limit,
                 cursor,
             }),
+            None::<()>,
         )
         .await
     }
+    /// Finds the public server of the place the user is playing in, `None` if there is none
+    ///
+    /// Servers only list the tokens of most of their players, so the url of the user's headshot
+    /// is compared with the ones of the tokens, page by page. `None` is also returned when the
+    /// headshot of the user is unavailable, as nothing can match it then.
+    ///
+    /// Requests wait for their quota, and rate limited ones are resent according to the retry
+    /// policy of the client. Dropping the future stops the search.
+    async fn find_user_in_servers(
+        &self,
+        place_id: Id,
+        user_id: Id,
+    ) -> RequestResult<Option<Uuid>, JsonError>
+    where
+        Self: Sized,
+    {
+        let headshot = |request: BatchRequest<String, String, ()>| BatchRequest {
+            r#type: ThumbnailType::AvatarHeadShot,
+            size: ThumbnailSize::_48x48,
+            ..request
+        };
+        let target = self
+            .get_batch_thumbnails_resolved(
+                [headshot(BatchRequest {
+                    target_id: user_id.into(),
+                    ..Default::default()
+                })],
+                HEADSHOT_TIMEOUT,
+            )
+            .await?;
+        let Some(Ok(target)) = target.into_iter().next() else {
+            return Ok(None);
+        };
+        let mut cursor = None::<String>;
+        loop {
+            let page = self
+                .get_public_servers_manual(
+                    place_id,
+                    ServerType::Public,
+                    SortOrder::default(),
+                    false,
+                    RequestLimit::OneHundred,
+                    cursor.take(),
+                )
+                .await?;
+            // Listed players need no thumbnail
+            if let Some(server) = page
+                .data
+                .iter()
+                .find(|server| server.players.iter().any(|player| player.id == user_id))
+            {
+                return Ok(Some(server.id));
+            }
+            let requests = page
+                .data
+                .iter()
+                .enumerate()
+                .flat_map(|(index, server)| {
+                    server.player_tokens.iter().map(move |token| {
+                        headshot(BatchRequest {
+                            request_id: Some(index.to_string()),
+                            token: Some(token.clone()),
+                            ..Default::default()
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !requests.is_empty() {
+                let thumbnails = self
+                    .get_batch_thumbnails_resolved(requests, HEADSHOT_TIMEOUT)
+                    .await?;
+                if let Some(server) = thumbnails
+                    .iter()
+                    .filter(|thumbnail| {
+                        thumbnail
+                            .as_ref()
+                            .is_ok_and(|thumbnail| thumbnail.image_url == target.image_url)
+                    })
+                    .find_map(|thumbnail| {
+                        page.data
+                            .get(thumbnail.request_id()?.parse::<usize>().ok()?)
+                    })
+                {
+                    return Ok(Some(server.id));
+                }
+            }
+            match page.next_page_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(None),
+            }
+        }
+    }
 }
 
 impl<T: BaseClient> GamesApi for T {}
@@ -1,16 +1,17 @@
 use async_trait::async_trait;
 use serde::Deserialize;
 
-use crate::BaseClient;
+use crate::{BaseClient, endpoints::Service};
 
 use super::{Id, JsonError, OptionId, RequestResult};
 
 macro_rules! add_base_url {
-    ($api_route: literal) => {
-        concat!("https://apis.roblox.com/", $api_route)
-    };
-    ($api_format_string: expr, $($args:expr),+) => {
-        format!(concat!("https://apis.roblox.com/", $api_format_string), $($args),+)
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::Apis)
+            $(, $args)*
+        )
     };
 }
 
@@ -25,7 +26,7 @@ pub trait GeneralApi: BaseClient {
     async fn get_universe_from_place(&self, place: Id) -> RequestResult<OptionId, JsonError> {
         let res = self
             .get::<PlaceResponse, _>(
-                add_base_url!("universes/v1/places/{}/universe", place),
+                add_base_url!(self.endpoints(), "universes/v1/places/{}/universe", place),
                 None::<()>,
             )
             .await?;
@@ -1,16 +1,53 @@
-use crate::{AuthenticatedClient, BaseClient, RequestResult};
+use crate::{
+    AuthenticatedClient, BaseClient, RequestResult, endpoints::Service, rate_limit::Quota,
+};
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use deranged::RangedU32;
 use futures::Stream;
 use itertools::Itertools;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
 
 use super::{
-    Empty, Id, JsonError, MembershipType, Page, RequestLimit, SortOrder, SortOrderDefaultAscending,
+    BatchChunk, Empty, Id, JsonError, Page, RequestLimit, SortOrder, SortOrderDefaultAscending,
+    pagination::{CursorStore, PageDirection, PageItems},
 };
 
+/// Rate limit of [`GroupsApi::get_group_info_batch`]
+pub const GROUP_INFO_BATCH_QUOTA: Quota =
+    Quota::new("groups/v2/groups", 100, Duration::from_mins(1));
+/// Most groups per [`GroupsApi::get_group_info_batch`] request
+pub const GROUP_INFO_BATCH_LIMIT: usize = 100;
+
+super::error_codes! {
+    /// Codes of [`JsonError`]s returned by the groups api
+    GroupsErrorCode {
+        /// The group is invalid or does not exist
+        InvalidGroup = 1,
+        /// The role is invalid or does not exist
+        InvalidRole = 2,
+        /// The user is invalid or does not exist
+        InvalidUser = 3,
+        /// You must pass the captcha test before joining this group
+        CaptchaRequired = 5,
+        /// You are already in the maximum number of groups
+        TooManyGroups = 6,
+        /// You have already requested to join this group
+        AlreadyRequested = 7,
+        /// You are already a member of this group
+        AlreadyInGroup = 8,
+        /// This group has been locked
+        GroupLocked = 9,
+        /// The operation is temporarily unavailable
+        TemporarilyUnavailable = 18,
+        /// Insufficient permissions to complete the request
+        InsufficientPermissions = 23,
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GroupShout {
     pub body: String,
@@ -131,11 +168,12 @@ struct GetGroupMembersParameters<T: Send> {
 }
 
 macro_rules! add_base_url {
-    ($api_route: literal) => {
-        concat!("https://groups.roblox.com/", $api_route)
-    };
-    ($api_format_string: expr, $($args:expr),+) => {
-        format!(concat!("https://groups.roblox.com/", $api_format_string), $($args),+)
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::Groups)
+            $(, $args)*
+        )
     };
 }
 #[async_trait]
@@ -149,20 +187,54 @@ pub trait GroupsApi: BaseClient {
     ) -> RequestResult<Vec<BatchGroupInfo>, JsonError> {
         let query_ids = groups.into_iter().join(",");
         let response = self
-            .get::<BatchResponse, _>(
-                add_base_url!("v2/groups"),
+            .request_with_quota::<BatchResponse, _>(
+                GROUP_INFO_BATCH_QUOTA,
+                Method::GET,
+                add_base_url!(self.endpoints(), "v2/groups"),
                 Some([("groupIds", query_ids.as_str())]),
+                None::<()>,
             )
             .await?;
         Ok(response.data)
     }
+    /// [`Self::get_group_info_batch`] for any number of groups, in chunks of
+    /// [`GROUP_INFO_BATCH_LIMIT`] with up to `concurrency` requests at a time
+    async fn get_group_info_batch_all(
+        &self,
+        groups: impl IntoIterator<Item = Id> + Send,
+        concurrency: usize,
+    ) -> RequestResult<Vec<BatchGroupInfo>, JsonError> {
+        super::merge_batch_chunks(super::batch_chunks(
+            groups,
+            GROUP_INFO_BATCH_LIMIT,
+            concurrency,
+            |groups| self.get_group_info_batch(groups),
+        ))
+        .await
+    }
+    /// Like [`Self::get_group_info_batch_all`], yielding every chunk even after one failed
+    fn get_group_info_batch_chunks(
+        &self,
+        groups: impl IntoIterator<Item = Id>,
+        concurrency: usize,
+    ) -> impl Stream<Item = BatchChunk<Id, BatchGroupInfo, JsonError>> {
+        super::batch_chunks(groups, GROUP_INFO_BATCH_LIMIT, concurrency, |groups| {
+            self.get_group_info_batch(groups)
+        })
+    }
     async fn get_group_info(&self, group: Id) -> RequestResult<SingleGroupInfo, JsonError> {
-        self.get(add_base_url!("v1/groups/{}", group), None::<()>)
-            .await
+        self.get(
+            add_base_url!(self.endpoints(), "v1/groups/{}", group),
+            None::<()>,
+        )
+        .await
     }
     async fn get_group_metadata(&self) -> RequestResult<GroupMetadata, JsonError> {
-        self.get(add_base_url!("v1/groups/metadata"), None::<()>)
-            .await
+        self.get(
+            add_base_url!(self.endpoints(), "v1/groups/metadata"),
+            None::<()>,
+        )
+        .await
     }
     fn get_group_members(
         &self,
@@ -179,6 +251,26 @@ pub trait GroupsApi: BaseClient {
             cursor,
         )
     }
+    /// Members one by one, resuming from the cursor saved in the store under `key`
+    ///
+    /// See [`PageItems::with_checkpoint`], so that crawling a large group survives restarts.
+    fn get_group_members_resumable(
+        &self,
+        group: Id,
+        limit: RequestLimit,
+        sort_order: SortOrder,
+        store: Arc<dyn CursorStore>,
+        key: &str,
+    ) -> PageItems<impl Stream<Item = RequestResult<Page<GroupMember>, JsonError>>, GroupMember>
+    {
+        let cursor = store.load(key);
+        PageItems::new(
+            self.get_group_members(group, limit, cursor.clone(), sort_order),
+            cursor,
+            PageDirection::Forward,
+        )
+        .with_checkpoint(store, key)
+    }
     async fn get_group_members_manual(
         &self,
         group: Id,
@@ -187,7 +279,7 @@ pub trait GroupsApi: BaseClient {
         sort_order: SortOrder,
     ) -> RequestResult<Page<GroupMember>, JsonError> {
         self.get(
-            add_base_url!("v1/groups/{}/users", group),
+            add_base_url!(self.endpoints(), "v1/groups/{}/users", group),
             Some(GetGroupMembersParameters {
                 limit,
                 cursor,
@@ -201,6 +293,8 @@ impl<T: BaseClient> GroupsApi for T {}
 
 #[async_trait]
 pub trait GroupsAuthenticatedApi: AuthenticatedClient {
+    /// Usually fails with [`Error::ChallengeRequired`](super::Error::ChallengeRequired) unless
+    /// the client has a [`ChallengeSolver`](crate::challenge::ChallengeSolver)
     async fn join_group<'a>(
         &self,
         group: Id,
@@ -215,12 +309,19 @@ pub trait GroupsAuthenticatedApi: AuthenticatedClient {
             >,
         >,
     ) -> RequestResult<Empty, JsonError> {
-        self.authenticated_post(add_base_url!("v1/groups/{}/users", group), solved_captcha)
-            .await
+        self.authenticated_post(
+            add_base_url!(self.authenticated_endpoints(), "v1/groups/{}/users", group),
+            solved_captcha,
+        )
+        .await
     }
     async fn claim_group(&self, group: Id) -> RequestResult<Empty, JsonError> {
         self.authenticated_post(
-            add_base_url!("v1/groups/{}/claim-ownership", group),
+            add_base_url!(
+                self.authenticated_endpoints(),
+                "v1/groups/{}/claim-ownership",
+                group
+            ),
             None::<()>,
         )
         .await
@@ -231,7 +332,12 @@ pub trait GroupsAuthenticatedApi: AuthenticatedClient {
         target: Id,
     ) -> RequestResult<Empty, JsonError> {
         self.authenticated_delete(
-            add_base_url!("v1/groups/{}/users/{}", group, target),
+            add_base_url!(
+                self.authenticated_endpoints(),
+                "v1/groups/{}/users/{}",
+                group,
+                target
+            ),
             None::<()>,
         )
         .await
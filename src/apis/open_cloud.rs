@@ -0,0 +1,226 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use derive_is_enum_variant::is_enum_variant;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{OpenCloudClient, endpoints::Service, private::RobloxErrorSealed};
+
+use super::{Empty, Id, RequestResult, RobloxError};
+
+macro_rules! add_base_url {
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}cloud/", $api_format_string),
+            $endpoints.get(Service::Apis)
+            $(, $args)*
+        )
+    };
+}
+
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, Eq, is_enum_variant)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OpenCloudErrorCode {
+    InvalidArgument,
+    InsufficientScope,
+    PermissionDenied,
+    Unauthenticated,
+    NotFound,
+    AlreadyExists,
+    Aborted,
+    ResourceExhausted,
+    Cancelled,
+    Internal,
+    NotImplemented,
+    Unavailable,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct InnerOpenCloudError {
+    #[serde(alias = "error")]
+    code: OpenCloudErrorCode,
+    message: String,
+}
+
+#[derive(Debug, Error, Clone, is_enum_variant)]
+pub enum OpenCloudError {
+    #[error("open cloud error: {code:?}: {message}")]
+    Valid {
+        code: OpenCloudErrorCode,
+        message: String,
+    },
+    /// The client was not granted a scope required by the endpoint, detected before sending
+    #[error("open cloud error: missing scope {0}")]
+    MissingScope(&'static str),
+    #[error("malformed response: {0}")]
+    Malformed(String),
+}
+impl RobloxErrorSealed for OpenCloudError {}
+impl RobloxError for OpenCloudError {
+    fn parse(res: String) -> Self {
+        sonic_rs::from_str::<InnerOpenCloudError>(&res).map_or(Self::Malformed(res), |value| {
+            Self::Valid {
+                code: value.code,
+                message: value.message,
+            }
+        })
+    }
+    #[inline]
+    fn is_structured(&self) -> bool {
+        !matches!(self, Self::Malformed(_))
+    }
+}
+impl OpenCloudError {
+    /// Whether the api key or token is invalid or lacks the scope or permission for the request
+    #[must_use]
+    pub const fn is_scope_error(&self) -> bool {
+        match self {
+            Self::Valid { code, .. } => matches!(
+                code,
+                OpenCloudErrorCode::InsufficientScope
+                    | OpenCloudErrorCode::PermissionDenied
+                    | OpenCloudErrorCode::Unauthenticated
+            ),
+            Self::MissingScope(_) => true,
+            Self::Malformed(_) => false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Universe {
+    pub path: String,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+    pub display_name: String,
+    pub description: String,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub visibility: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudUser {
+    pub path: String,
+    pub create_time: DateTime<Utc>,
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+    pub about: String,
+    pub locale: String,
+    pub premium: Option<bool>,
+    pub id_verified: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudGroupMembership {
+    pub path: String,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+    pub user: String,
+    pub role: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudGroupMembershipPage {
+    #[serde(default)]
+    pub group_memberships: Vec<CloudGroupMembership>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ListParameters<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_page_size: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_token: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct PublishMessageRequest<'a> {
+    topic: &'a str,
+    message: &'a str,
+}
+
+fn require_scope<C: OpenCloudClient + ?Sized>(
+    client: &C,
+    scope: &'static str,
+) -> RequestResult<(), OpenCloudError> {
+    if client.has_scope(scope) {
+        Ok(())
+    } else {
+        Err(OpenCloudError::MissingScope(scope).into())
+    }
+}
+
+#[async_trait]
+pub trait OpenCloudApi: OpenCloudClient {
+    async fn get_universe(&self, universe: Id) -> RequestResult<Universe, OpenCloudError> {
+        self.get(
+            add_base_url!(self.endpoints(), "v2/universes/{}", universe),
+            None::<()>,
+        )
+        .await
+    }
+    /// Scope: `universe:write`
+    async fn restart_universe_servers(&self, universe: Id) -> RequestResult<Empty, OpenCloudError> {
+        require_scope(self, "universe:write")?;
+        self.post(
+            add_base_url!(self.endpoints(), "v2/universes/{}:restartServers", universe),
+            Some(Empty {}),
+        )
+        .await
+    }
+    /// Scope: `universe-messaging-service:publish`
+    async fn publish_message(
+        &self,
+        universe: Id,
+        topic: &str,
+        message: &str,
+    ) -> RequestResult<Empty, OpenCloudError> {
+        require_scope(self, "universe-messaging-service:publish")?;
+        self.post(
+            add_base_url!(self.endpoints(), "v2/universes/{}:publishMessage", universe),
+            Some(PublishMessageRequest { topic, message }),
+        )
+        .await
+    }
+    async fn get_user(&self, user: Id) -> RequestResult<CloudUser, OpenCloudError> {
+        self.get(
+            add_base_url!(self.endpoints(), "v2/users/{}", user),
+            None::<()>,
+        )
+        .await
+    }
+    /// Scope: `group:read`
+    async fn list_group_memberships(
+        &self,
+        group: Id,
+        max_page_size: Option<u16>,
+        page_token: Option<&str>,
+        filter: Option<&str>,
+    ) -> RequestResult<CloudGroupMembershipPage, OpenCloudError> {
+        require_scope(self, "group:read")?;
+        self.get(
+            add_base_url!(self.endpoints(), "v2/groups/{}/memberships", group),
+            Some(ListParameters {
+                max_page_size,
+                page_token,
+                filter,
+            }),
+        )
+        .await
+    }
+}
+
+impl<T: OpenCloudClient> OpenCloudApi for T {}
@@ -0,0 +1,247 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Debug},
+    fs,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, ready},
+};
+
+use futures::Stream;
+
+use super::{Page, RequestResult, RobloxError};
+
+/// Which cursor of a [`Page`] leads to the next one
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum PageDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+impl PageDirection {
+    #[must_use]
+    pub fn cursor<T>(self, page: &Page<T>) -> Option<&str> {
+        match self {
+            Self::Forward => page.next_page_cursor.as_deref(),
+            Self::Backward => page.previous_page_cursor.as_deref(),
+        }
+    }
+}
+
+/// Keeps the cursor a crawl reached, so that it can resume after a restart
+pub trait CursorStore: Debug + Send + Sync {
+    fn load(&self, key: &str) -> Option<String>;
+    fn save(&self, key: &str, cursor: &str);
+    /// Called once the crawl is complete
+    fn remove(&self, key: &str);
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryCursorStore {
+    cursors: Mutex<HashMap<String, String>>,
+}
+impl CursorStore for MemoryCursorStore {
+    fn load(&self, key: &str) -> Option<String> {
+        self.cursors.lock().unwrap().get(key).cloned()
+    }
+    fn save(&self, key: &str, cursor: &str) {
+        self.cursors
+            .lock()
+            .unwrap()
+            .insert(String::from(key), String::from(cursor));
+    }
+    fn remove(&self, key: &str) {
+        self.cursors.lock().unwrap().remove(key);
+    }
+}
+
+/// Cursors kept in a json file, rewritten whenever one changes
+#[derive(Debug)]
+pub struct FileCursorStore {
+    path: PathBuf,
+    cursors: Mutex<HashMap<String, String>>,
+}
+impl FileCursorStore {
+    /// Loads the cursors saved in the file, if it exists
+    pub fn new(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let cursors = match fs::read(&path) {
+            Ok(data) => sonic_rs::from_slice(&data).map_err(std::io::Error::other)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error),
+        };
+        Ok(Self {
+            path,
+            cursors: Mutex::new(cursors),
+        })
+    }
+    fn update(&self, update: impl FnOnce(&mut HashMap<String, String>)) {
+        let mut cursors = self.cursors.lock().unwrap();
+        update(&mut cursors);
+        let data = sonic_rs::to_string(&*cursors).unwrap();
+        drop(cursors);
+        if let Err(error) = fs::write(&self.path, data) {
+            tracing::warn!(%error, path = %self.path.display(), "failed to save cursors");
+        }
+    }
+}
+impl CursorStore for FileCursorStore {
+    fn load(&self, key: &str) -> Option<String> {
+        self.cursors.lock().unwrap().get(key).cloned()
+    }
+    fn save(&self, key: &str, cursor: &str) {
+        self.update(|cursors| {
+            cursors.insert(String::from(key), String::from(cursor));
+        });
+    }
+    fn remove(&self, key: &str) {
+        self.update(|cursors| {
+            cursors.remove(key);
+        });
+    }
+}
+
+type Predicate<T> = Box<dyn FnMut(&T) -> bool + Send>;
+
+/// Flattens a stream of pages into their items
+///
+/// [`Self::resume_cursor`] is the cursor of the first page not completely yielded yet, so
+/// passing it back to the endpoint resumes the crawl, possibly repeating some items.
+pub struct PageItems<S, T> {
+    pages: Pin<Box<S>>,
+    direction: PageDirection,
+    items: VecDeque<T>,
+    cursor: Option<String>,
+    next_cursor: Option<String>,
+    remaining: Option<usize>,
+    take_while: Option<Predicate<T>>,
+    checkpoint: Option<(Arc<dyn CursorStore>, String)>,
+    exhausted: bool,
+    done: bool,
+}
+impl<S, T> Debug for PageItems<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageItems")
+            .field("direction", &self.direction)
+            .field("cursor", &self.cursor)
+            .field("remaining", &self.remaining)
+            .field("exhausted", &self.exhausted)
+            .finish_non_exhaustive()
+    }
+}
+// Only the page stream is pinned, and it is boxed
+impl<S, T> Unpin for PageItems<S, T> {}
+impl<S, T> PageItems<S, T> {
+    /// `cursor` must be the one the first page was requested with
+    #[must_use]
+    pub fn new(pages: S, cursor: Option<impl Into<String>>, direction: PageDirection) -> Self {
+        Self {
+            pages: Box::pin(pages),
+            direction,
+            items: VecDeque::new(),
+            cursor: cursor.map(Into::into),
+            next_cursor: None,
+            remaining: None,
+            take_while: None,
+            checkpoint: None,
+            exhausted: false,
+            done: false,
+        }
+    }
+    /// Stops after `count` items
+    #[must_use]
+    pub const fn limit(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+    /// Stops at the first item not matching the predicate, without yielding it
+    #[must_use]
+    pub fn take_while(mut self, predicate: impl FnMut(&T) -> bool + Send + 'static) -> Self {
+        self.take_while = Some(Box::new(predicate));
+        self
+    }
+    /// Saves the resume cursor in the store under `key` whenever a page is completed, and
+    /// removes it once the last page is
+    #[must_use]
+    pub fn with_checkpoint(mut self, store: Arc<dyn CursorStore>, key: &str) -> Self {
+        self.checkpoint = Some((store, String::from(key)));
+        self
+    }
+    #[inline]
+    #[must_use]
+    pub fn resume_cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+    /// Whether every page was yielded, the resume cursor then being of no use
+    #[inline]
+    #[must_use]
+    pub const fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+    fn complete_page(&mut self) {
+        self.cursor = self.next_cursor.take();
+        self.exhausted = self.cursor.is_none();
+        if let Some((store, key)) = &self.checkpoint {
+            match &self.cursor {
+                Some(cursor) => store.save(key, cursor),
+                None => store.remove(key),
+            }
+        }
+    }
+}
+
+impl<S, T, E> Stream for PageItems<S, T>
+where
+    S: Stream<Item = RequestResult<Page<T>, E>>,
+    E: RobloxError,
+{
+    type Item = RequestResult<T, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done || this.remaining == Some(0) {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            if let Some(item) = this.items.pop_front() {
+                if let Some(predicate) = &mut this.take_while
+                    && !predicate(&item)
+                {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                if this.items.is_empty() {
+                    this.complete_page();
+                }
+                if let Some(remaining) = &mut this.remaining {
+                    *remaining -= 1;
+                }
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if this.exhausted {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            match ready!(this.pages.as_mut().poll_next(cx)) {
+                Some(Ok(page)) => {
+                    this.next_cursor = this.direction.cursor(&page).map(String::from);
+                    this.items = VecDeque::from(page.data);
+                    if this.items.is_empty() {
+                        this.complete_page();
+                    }
+                }
+                Some(Err(error)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(error)));
+                }
+                None => {
+                    this.exhausted = true;
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
@@ -1,22 +1,56 @@
 use async_trait::async_trait;
+use bytes::Bytes;
 use derive_is_enum_variant::is_enum_variant;
+use futures::Stream;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_repr::Serialize_repr;
+use sha2::{Digest, Sha256};
+use sonic_rs::JsonValueTrait;
 use thiserror::Error;
+use tokio::time::Instant;
 
-use crate::{BaseClient, private::BatchThumbnailResultExtSealed};
+use std::{fmt::Write, time::Duration};
 
-use super::{JsonError, OptionId, RequestResult};
+use crate::{
+    BaseClient, endpoints::Service, private::BatchThumbnailResultExtSealed, rate_limit::Quota,
+    retry::RetryPolicy,
+};
+
+use super::{BatchChunk, JsonError, OptionId, RequestResult, StringError};
 
 macro_rules! add_base_url {
-    ($api_route: literal) => {
-        concat!("https://thumbnails.roblox.com/", $api_route)
-    };
-    ($api_format_string: expr, $($args:expr),+) => {
-        format!(concat!("https://thumbnails.roblox.com/", $api_format_string), $($args),+)
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::Thumbnails)
+            $(, $args)*
+        )
     };
 }
 
+/// Rate limit of [`ThumbnailsApi::get_batch_thumbnails`]
+pub const BATCH_THUMBNAILS_QUOTA: Quota =
+    Quota::new("thumbnails/v1/batch", 50, Duration::from_millis(1500));
+/// Most thumbnails per [`ThumbnailsApi::get_batch_thumbnails`] request
+pub const BATCH_THUMBNAILS_LIMIT: usize = 100;
+/// Concurrent requests made by [`ThumbnailsApi::get_batch_thumbnails_resolved`]
+const RESOLVE_CONCURRENCY: usize = 4;
+
+super::error_codes! {
+    /// Codes of [`JsonError`]s returned by the thumbnails api
+    ThumbnailsErrorCode {
+        /// There are too many requested ids
+        TooManyIds = 1,
+        /// The requested size is invalid
+        InvalidSize = 2,
+        /// The requested ids are invalid, of an invalid type or missing
+        InvalidIds = 3,
+        /// The requested format is invalid
+        InvalidFormat = 4,
+    }
+}
+
 #[derive(Debug, Default, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchRequest<T1: Send, T2: Send, T3: Send> {
@@ -44,6 +78,69 @@ pub enum ThumbnailFormat {
     Jpeg,
 }
 
+impl ThumbnailFormat {
+    /// The format of an image, from its first bytes
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(Self::Jpeg)
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+}
+
+/// Image of a thumbnail, see [`ThumbnailsApi::download_thumbnail`]
+#[derive(Debug, Clone)]
+pub struct ThumbnailImage {
+    pub bytes: Bytes,
+    /// `None` if the content is in none of the known formats
+    pub format: Option<ThumbnailFormat>,
+}
+impl ThumbnailImage {
+    #[must_use]
+    pub fn new(bytes: Bytes) -> Self {
+        Self {
+            format: ThumbnailFormat::detect(&bytes),
+            bytes,
+        }
+    }
+    /// Sha-256 of the content, the same for identical images
+    #[must_use]
+    pub fn content_hash(&self) -> [u8; 32] {
+        Sha256::digest(&self.bytes).into()
+    }
+    /// [`Self::content_hash`] as lowercase hex, e.g. to name archived files
+    #[must_use]
+    pub fn content_hash_hex(&self) -> String {
+        self.content_hash()
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                write!(hex, "{byte:02x}").unwrap();
+                hex
+            })
+    }
+    #[cfg(feature = "image")]
+    pub fn decode(&self) -> image::ImageResult<image::DynamicImage> {
+        match self.format {
+            Some(ThumbnailFormat::Png) => {
+                image::load_from_memory_with_format(&self.bytes, image::ImageFormat::Png)
+            }
+            Some(ThumbnailFormat::Jpeg) => {
+                image::load_from_memory_with_format(&self.bytes, image::ImageFormat::Jpeg)
+            }
+            Some(ThumbnailFormat::Webp) => {
+                image::load_from_memory_with_format(&self.bytes, image::ImageFormat::WebP)
+            }
+            None => image::load_from_memory(&self.bytes),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Default, is_enum_variant, Clone, Copy)]
 pub enum ThumbnailSize {
     #[default]
@@ -154,6 +251,8 @@ pub enum ThumbnailErrorState {
     InReview,
     Pending,
     TemporarilyUnavailable,
+    /// Never returned before the timeout of [`ThumbnailsApi::get_batch_thumbnails_resolved`]
+    TimedOut,
 }
 
 #[allow(clippy::fallible_impl_from)]
@@ -233,6 +332,34 @@ impl BatchThumbnailResultExt for BatchThumbnailResult {
     }
 }
 
+/// Whether requesting the thumbnail again later may complete it
+const fn is_unresolved(result: &BatchThumbnailResult) -> bool {
+    matches!(
+        result,
+        Err(BatchThumbnailError {
+            state: ThumbnailErrorState::Pending | ThumbnailErrorState::TemporarilyUnavailable,
+            ..
+        })
+    )
+}
+
+fn set_request_id(result: &mut BatchThumbnailResult, request_id: Option<String>) {
+    match result {
+        Ok(thumbnail) => thumbnail.request_id = request_id,
+        Err(thumbnail) => thumbnail.request_id = request_id,
+    }
+}
+
+/// The request id as it would be sent back in the response
+fn request_id_string(request_id: &impl Serialize) -> Option<String> {
+    let value = sonic_rs::to_value(request_id).ok()?;
+    Some(
+        value
+            .as_str()
+            .map_or_else(|| value.to_string(), String::from),
+    )
+}
+
 #[allow(clippy::fallible_impl_from)]
 impl From<InnerBatchThumbnail> for BatchThumbnailResult {
     fn from(value: InnerBatchThumbnail) -> Self {
@@ -285,13 +412,145 @@ pub trait ThumbnailsApi: BaseClient {
         I::IntoIter: Send + Clone,
     {
         let res = self
-            .post::<BatchResponse, _>(
-                add_base_url!("v1/batch"),
+            .request_with_quota::<BatchResponse, _>(
+                BATCH_THUMBNAILS_QUOTA,
+                Method::POST,
+                add_base_url!(self.endpoints(), "v1/batch"),
+                None::<()>,
                 Some(BatchRequestArray(requests.into_iter())),
             )
             .await?;
         Ok(res.data.into_iter().map(Into::into).collect())
     }
+    /// [`Self::get_batch_thumbnails`] for any number of thumbnails, in chunks of
+    /// [`BATCH_THUMBNAILS_LIMIT`] with up to `concurrency` requests at a time
+    async fn get_batch_thumbnails_all<T1, T2, T3>(
+        &self,
+        requests: impl IntoIterator<Item = BatchRequest<T1, T2, T3>> + Send,
+        concurrency: usize,
+    ) -> RequestResult<Vec<BatchThumbnailResult>, JsonError>
+    where
+        T1: Serialize + Clone + Send,
+        T2: Serialize + Clone + Send,
+        T3: Serialize + Clone + Send,
+    {
+        super::merge_batch_chunks(super::batch_chunks(
+            requests,
+            BATCH_THUMBNAILS_LIMIT,
+            concurrency,
+            |requests| self.get_batch_thumbnails(requests),
+        ))
+        .await
+    }
+    /// [`Self::get_batch_thumbnails_all`], requesting the pending or temporarily unavailable
+    /// thumbnails again with backoff until they resolve or the timeout passes
+    ///
+    /// Results are in the order of the requests, with their `request_id`, one for each. Those
+    /// still unresolved at the timeout are in their last state, or [`ThumbnailErrorState::TimedOut`]
+    /// if they never had one.
+    async fn get_batch_thumbnails_resolved<T1, T2, T3>(
+        &self,
+        requests: impl IntoIterator<Item = BatchRequest<T1, T2, T3>> + Send,
+        timeout: Duration,
+    ) -> RequestResult<Vec<BatchThumbnailResult>, JsonError>
+    where
+        T1: Serialize + Send,
+        T2: Serialize + Clone + Send + Sync,
+        T3: Serialize + Clone + Send + Sync,
+    {
+        let deadline = Instant::now() + timeout;
+        let backoff = RetryPolicy::default()
+            .with_base_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(10));
+        // Requests are sent with their index as id, mapped back to the caller's ids at the end
+        let (request_ids, requests): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .enumerate()
+            .map(|(index, request)| {
+                (
+                    request.request_id.as_ref().and_then(request_id_string),
+                    BatchRequest {
+                        request_id: Some(index.to_string()),
+                        target_id: request.target_id,
+                        token: request.token,
+                        alias: request.alias,
+                        r#type: request.r#type,
+                        size: request.size,
+                        format: request.format,
+                        circular: request.circular,
+                    },
+                )
+            })
+            .unzip();
+        let mut results = vec![None; requests.len()];
+        let mut unresolved = (0..requests.len()).collect::<Vec<_>>();
+        for attempt in 1.. {
+            let batch = self
+                .get_batch_thumbnails_all(
+                    unresolved.iter().map(|index| requests[*index].clone()),
+                    RESOLVE_CONCURRENCY,
+                )
+                .await?;
+            for result in batch {
+                if let Some(index) = result
+                    .request_id()
+                    .and_then(|request_id| request_id.parse::<usize>().ok())
+                    .filter(|index| *index < results.len())
+                {
+                    results[index] = Some(result);
+                }
+            }
+            unresolved.retain(|index| results[*index].as_ref().is_none_or(is_unresolved));
+            if unresolved.is_empty() {
+                break;
+            }
+            let delay = backoff.backoff(attempt);
+            if Instant::now() + delay >= deadline {
+                break;
+            }
+            tokio::time::sleep(delay).await;
+        }
+        Ok(results
+            .into_iter()
+            .zip(requests)
+            .zip(request_ids)
+            .map(|((result, request), request_id)| {
+                let mut result = result.unwrap_or_else(|| {
+                    Err(BatchThumbnailError {
+                        target_id: request.target_id,
+                        state: ThumbnailErrorState::TimedOut,
+                        ..Default::default()
+                    })
+                });
+                set_request_id(&mut result, request_id);
+                result
+            })
+            .collect())
+    }
+    /// Downloads the image of a completed thumbnail, through the client like any other request
+    async fn download_thumbnail(
+        &self,
+        thumbnail: &BatchThumbnail,
+    ) -> RequestResult<ThumbnailImage, StringError> {
+        self.download(thumbnail.image_url.as_str())
+            .await
+            .map(ThumbnailImage::new)
+    }
+    /// Like [`Self::get_batch_thumbnails_all`], yielding every chunk even after one failed
+    fn get_batch_thumbnails_chunks<'a, T1, T2, T3>(
+        &'a self,
+        requests: impl IntoIterator<Item = BatchRequest<T1, T2, T3>>,
+        concurrency: usize,
+    ) -> impl Stream<Item = BatchChunk<BatchRequest<T1, T2, T3>, BatchThumbnailResult, JsonError>>
+    where
+        T1: Serialize + Clone + Send + 'a,
+        T2: Serialize + Clone + Send + 'a,
+        T3: Serialize + Clone + Send + 'a,
+    {
+        super::batch_chunks(requests, BATCH_THUMBNAILS_LIMIT, concurrency, |requests| {
+            self.get_batch_thumbnails(requests)
+        })
+    }
 }
 
 impl<T: BaseClient> ThumbnailsApi for T {}
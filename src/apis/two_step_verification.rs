@@ -0,0 +1,296 @@
+use std::fmt::{self, Debug};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha1::Sha1;
+
+use crate::{
+    AuthenticatedClient, RequestResult,
+    challenge::{Challenge, ChallengeSolver, ChallengeType, SolvedChallenge},
+    endpoints::Service,
+};
+
+use super::{Empty, Id, JsonError};
+
+const TOTP_STEP_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+
+/// Metadata of a [`ChallengeType::TwoStepVerification`] challenge
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoStepVerificationChallenge {
+    #[serde(deserialize_with = "deserialize_string_id")]
+    pub user_id: Id,
+    pub challenge_id: String,
+    pub action_type: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwoStepVerificationMethod {
+    Authenticator,
+    Email,
+    Sms,
+    RecoveryCode,
+}
+impl TwoStepVerificationMethod {
+    const fn path(self) -> &'static str {
+        match self {
+            Self::Authenticator => "authenticator",
+            Self::Email => "email",
+            Self::Sms => "sms",
+            Self::RecoveryCode => "recovery-codes",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendCodeRequest<'a> {
+    challenge_id: &'a str,
+    action_type: &'a str,
+}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyCodeRequest<'a> {
+    challenge_id: &'a str,
+    action_type: &'a str,
+    code: &'a str,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyCodeResponse {
+    verification_token: String,
+}
+#[derive(Serialize)]
+struct ContinueChallengeRequest<'a> {
+    #[serde(rename = "challengeId")]
+    id: &'a str,
+    #[serde(rename = "challengeType")]
+    r#type: &'a str,
+    #[serde(rename = "challengeMetadata")]
+    metadata: &'a str,
+}
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinuedChallenge {
+    pub challenge_id: String,
+    pub challenge_type: String,
+    pub challenge_metadata: String,
+}
+/// Metadata sent back with the original request once verified
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifiedMetadata<'a> {
+    verification_token: &'a str,
+    remember_device: bool,
+    challenge_id: &'a str,
+    action_type: &'a str,
+}
+
+fn deserialize_string_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Id, D::Error> {
+    use serde::de::Error;
+    let id = String::deserialize(deserializer)?;
+    id.parse::<u64>()
+        .ok()
+        .and_then(Id::new)
+        .ok_or_else(|| D::Error::custom(format!("invalid user id {id}")))
+}
+
+macro_rules! add_base_url {
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::TwoStepVerification)
+            $(, $args)*
+        )
+    };
+}
+
+#[async_trait]
+pub trait TwoStepVerificationApi: AuthenticatedClient {
+    /// Only needed for [`TwoStepVerificationMethod::Email`] and [`TwoStepVerificationMethod::Sms`]
+    async fn send_verification_code(
+        &self,
+        challenge: &TwoStepVerificationChallenge,
+        method: TwoStepVerificationMethod,
+    ) -> RequestResult<Empty, JsonError> {
+        self.authenticated_post(
+            add_base_url!(
+                self.authenticated_endpoints(),
+                "v1/users/{}/challenges/{}/send-code",
+                challenge.user_id,
+                method.path()
+            ),
+            Some(SendCodeRequest {
+                challenge_id: &challenge.challenge_id,
+                action_type: &challenge.action_type,
+            }),
+        )
+        .await
+    }
+    /// Returns the verification token
+    async fn verify_code(
+        &self,
+        challenge: &TwoStepVerificationChallenge,
+        method: TwoStepVerificationMethod,
+        code: &str,
+    ) -> RequestResult<String, JsonError> {
+        Ok(self
+            .authenticated_post::<VerifyCodeResponse, _>(
+                add_base_url!(
+                    self.authenticated_endpoints(),
+                    "v1/users/{}/challenges/{}/verify",
+                    challenge.user_id,
+                    method.path()
+                ),
+                Some(VerifyCodeRequest {
+                    challenge_id: &challenge.challenge_id,
+                    action_type: &challenge.action_type,
+                    code,
+                }),
+            )
+            .await?
+            .verification_token)
+    }
+    /// Marks a solved challenge as completed, before the original request is resubmitted
+    async fn continue_challenge(
+        &self,
+        challenge_id: &str,
+        challenge_type: &ChallengeType,
+        metadata: &str,
+    ) -> RequestResult<ContinuedChallenge, JsonError> {
+        self.authenticated_post(
+            format!(
+                "{}challenge/v1/continue",
+                self.authenticated_endpoints().get(Service::Apis)
+            ),
+            Some(ContinueChallengeRequest {
+                id: challenge_id,
+                r#type: challenge_type.as_str(),
+                metadata,
+            }),
+        )
+        .await
+    }
+}
+
+impl<T: AuthenticatedClient> TwoStepVerificationApi for T {}
+
+/// Authenticator app secret, generating the same time based codes as the app
+#[derive(Clone)]
+pub struct Totp {
+    key: Vec<u8>,
+}
+impl Debug for Totp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Totp").finish_non_exhaustive()
+    }
+}
+impl Totp {
+    /// From the base32 secret shown when setting up the authenticator
+    #[must_use]
+    pub fn from_base32(secret: &str) -> Option<Self> {
+        decode_base32(secret).map(|key| Self { key })
+    }
+    #[must_use]
+    pub fn code_at(&self, time: DateTime<Utc>) -> String {
+        #[allow(clippy::cast_sign_loss)]
+        let counter = time.timestamp().div_euclid(TOTP_STEP_SECONDS) as u64;
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.key).unwrap();
+        mac.update(&counter.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = usize::from(hash[hash.len() - 1] & 0xf);
+        let binary = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(TOTP_DIGITS),
+            width = TOTP_DIGITS as usize
+        )
+    }
+    #[must_use]
+    pub fn code(&self) -> String {
+        self.code_at(Utc::now())
+    }
+}
+
+fn decode_base32(secret: &str) -> Option<Vec<u8>> {
+    let mut key = Vec::with_capacity(secret.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for character in secret.bytes() {
+        let value = match character.to_ascii_uppercase() {
+            character @ b'A'..=b'Z' => character - b'A',
+            character @ b'2'..=b'7' => character - b'2' + 26,
+            b' ' | b'-' | b'=' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            #[allow(clippy::cast_possible_truncation)]
+            key.push((buffer >> bits) as u8);
+        }
+    }
+    (!key.is_empty()).then_some(key)
+}
+
+/// Solves two step verification challenges with an authenticator secret
+///
+/// The client must not itself use this solver, see
+/// [`CookieClient::with_two_step_verification`](crate::clients::CookieClient::with_two_step_verification).
+#[derive(Debug)]
+pub struct TwoStepVerificationSolver<C> {
+    client: C,
+    totp: Totp,
+}
+impl<C> TwoStepVerificationSolver<C> {
+    #[must_use]
+    pub const fn new(client: C, totp: Totp) -> Self {
+        Self { client, totp }
+    }
+}
+
+#[async_trait]
+impl<C: AuthenticatedClient + Debug + Send + 'static> ChallengeSolver
+    for TwoStepVerificationSolver<C>
+{
+    async fn solve(&self, challenge: &Challenge) -> Option<SolvedChallenge> {
+        if challenge.r#type != ChallengeType::TwoStepVerification {
+            return None;
+        }
+        let metadata = challenge
+            .metadata_as::<TwoStepVerificationChallenge>()
+            .inspect_err(|error| tracing::warn!(%error, "malformed two step verification metadata"))
+            .ok()?;
+        let verification_token = self
+            .client
+            .verify_code(
+                &metadata,
+                TwoStepVerificationMethod::Authenticator,
+                &self.totp.code(),
+            )
+            .await
+            .inspect_err(|error| tracing::warn!(%error, "two step verification failed"))
+            .ok()?;
+        let verified = sonic_rs::to_string(&VerifiedMetadata {
+            verification_token: &verification_token,
+            remember_device: false,
+            challenge_id: &metadata.challenge_id,
+            action_type: &metadata.action_type,
+        })
+        .unwrap();
+        self.client
+            .continue_challenge(&challenge.id, &challenge.r#type, &verified)
+            .await
+            .inspect_err(|error| tracing::warn!(%error, "failed to continue challenge"))
+            .ok()?;
+        Some(SolvedChallenge {
+            id: challenge.id.clone(),
+            r#type: challenge.r#type.clone(),
+            metadata: verified,
+        })
+    }
+}
@@ -1,9 +1,28 @@
 use async_trait::async_trait;
+use futures::Stream;
 use serde::{Deserialize, Serialize};
 
-use crate::{AuthenticatedClient, BaseClient, RequestResult};
+use crate::{AuthenticatedClient, BaseClient, RequestResult, endpoints::Service};
 
-use super::{Id, JsonError};
+use super::{BatchChunk, Id, JsonError};
+
+/// Most users per [`UsersApi::get_user_info_from_id_batch`] or
+/// [`UsersApi::get_user_info_from_username_batch`] request
+pub const USERS_BATCH_LIMIT: usize = 200;
+
+super::error_codes! {
+    /// Codes of [`JsonError`]s returned by the users api
+    UsersErrorCode {
+        /// Too many ids or usernames were requested
+        TooManyIds = 2,
+        /// The user id is invalid
+        InvalidUser = 3,
+        /// The display name is too short or too long
+        InvalidDisplayNameLength = 5,
+        /// The display name was changed too recently
+        DisplayNameChangeCooldown = 6,
+    }
+}
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -54,19 +73,23 @@ struct BatchUserInfoFromUsernameResponse {
     data: Vec<BatchUserInfoFromUsername>,
 }
 macro_rules! add_base_url {
-    ($api_route: literal) => {
-        concat!("https://users.roblox.com/", $api_route)
-    };
-    ($api_format_string: expr, $($args:expr),+) => {
-        format!(concat!("https://users.roblox.com/", $api_format_string), $($args),+)
+    ($endpoints: expr, $api_format_string: literal $(, $args: expr)*) => {
+        format!(
+            concat!("{}", $api_format_string),
+            $endpoints.get(Service::Users)
+            $(, $args)*
+        )
     };
 }
 
 #[async_trait]
 pub trait UsersAuthenticatedApi: AuthenticatedClient {
     async fn get_authenticated(&self) -> RequestResult<AuthenticatedUser, JsonError> {
-        self.authenticated_get(add_base_url!("v1/users/authenticated"), None::<()>)
-            .await
+        self.authenticated_get(
+            add_base_url!(self.authenticated_endpoints(), "v1/users/authenticated"),
+            None::<()>,
+        )
+        .await
     }
 }
 
@@ -88,7 +111,7 @@ pub trait UsersApi: BaseClient {
     {
         let res = self
             .post::<BatchUserInfoFromIdResponse, _>(
-                add_base_url!("v1/users"),
+                add_base_url!(self.endpoints(), "v1/users"),
                 Some(BatchUserInfoFromIdRequest {
                     user_ids: users.into_iter(),
                     exclude_banned_users,
@@ -110,7 +133,7 @@ pub trait UsersApi: BaseClient {
     {
         let res = self
             .post::<BatchUserInfoFromUsernameResponse, _>(
-                add_base_url!("v1/usernames/users"),
+                add_base_url!(self.endpoints(), "v1/usernames/users"),
                 Some(BatchUserInfoFromUsernameRequest {
                     usernames: users.into_iter(),
                     exclude_banned_users,
@@ -119,6 +142,67 @@ pub trait UsersApi: BaseClient {
             .await?;
         Ok(res.data)
     }
+    /// [`Self::get_user_info_from_id_batch`] for any number of users, in chunks of
+    /// [`USERS_BATCH_LIMIT`] with up to `concurrency` requests at a time
+    async fn get_user_info_from_id_batch_all(
+        &self,
+        users: impl IntoIterator<Item = Id> + Send,
+        exclude_banned_users: bool,
+        concurrency: usize,
+    ) -> RequestResult<Vec<BatchUserInfoFromId>, JsonError> {
+        super::merge_batch_chunks(super::batch_chunks(
+            users,
+            USERS_BATCH_LIMIT,
+            concurrency,
+            |users| self.get_user_info_from_id_batch(users, exclude_banned_users),
+        ))
+        .await
+    }
+    /// Like [`Self::get_user_info_from_id_batch_all`], yielding every chunk even after one failed
+    fn get_user_info_from_id_batch_chunks(
+        &self,
+        users: impl IntoIterator<Item = Id>,
+        exclude_banned_users: bool,
+        concurrency: usize,
+    ) -> impl Stream<Item = BatchChunk<Id, BatchUserInfoFromId, JsonError>> {
+        super::batch_chunks(users, USERS_BATCH_LIMIT, concurrency, move |users| {
+            self.get_user_info_from_id_batch(users, exclude_banned_users)
+        })
+    }
+    /// [`Self::get_user_info_from_username_batch`] for any number of users, in chunks of
+    /// [`USERS_BATCH_LIMIT`] with up to `concurrency` requests at a time
+    async fn get_user_info_from_username_batch_all<T>(
+        &self,
+        users: impl IntoIterator<Item = T> + Send,
+        exclude_banned_users: bool,
+        concurrency: usize,
+    ) -> RequestResult<Vec<BatchUserInfoFromUsername>, JsonError>
+    where
+        T: Serialize + Clone + Send,
+    {
+        super::merge_batch_chunks(super::batch_chunks(
+            users,
+            USERS_BATCH_LIMIT,
+            concurrency,
+            |users| self.get_user_info_from_username_batch(users, exclude_banned_users),
+        ))
+        .await
+    }
+    /// Like [`Self::get_user_info_from_username_batch_all`], yielding every chunk even after one
+    /// failed
+    fn get_user_info_from_username_batch_chunks<'a, T>(
+        &'a self,
+        users: impl IntoIterator<Item = T>,
+        exclude_banned_users: bool,
+        concurrency: usize,
+    ) -> impl Stream<Item = BatchChunk<T, BatchUserInfoFromUsername, JsonError>>
+    where
+        T: Serialize + Clone + Send + 'a,
+    {
+        super::batch_chunks(users, USERS_BATCH_LIMIT, concurrency, move |users| {
+            self.get_user_info_from_username_batch(users, exclude_banned_users)
+        })
+    }
 }
 
 impl<T: BaseClient> UsersApi for T {}
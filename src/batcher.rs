@@ -0,0 +1,268 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::Hash,
+    mem,
+    pin::pin,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use ahash::RandomState;
+use futures::future::{self, BoxFuture, FutureExt, Shared};
+use tokio::sync::Notify;
+
+use crate::{
+    BaseClient,
+    apis::{
+        Error, Id, JsonError, RequestResult,
+        groups::{BatchGroupInfo, GROUP_INFO_BATCH_LIMIT, GroupsApi},
+        thumbnails::{
+            BATCH_THUMBNAILS_LIMIT, BatchRequest, BatchThumbnailResult, BatchThumbnailResultExt,
+            ThumbnailFormat, ThumbnailSize, ThumbnailType, ThumbnailsApi,
+        },
+        users::{BatchUserInfoFromId, USERS_BATCH_LIMIT, UsersApi},
+    },
+};
+
+const DEFAULT_WINDOW: Duration = Duration::from_millis(10);
+
+/// Error of a whole batch, shared by every lookup in it
+pub type BatchError = Arc<Error<JsonError>>;
+
+type Fetch<K, V> =
+    Arc<dyn Fn(Vec<K>) -> BoxFuture<'static, RequestResult<Vec<V>, JsonError>> + Send + Sync>;
+type BatchOutcome<K, V> = Result<Arc<HashMap<K, V, RandomState>>, BatchError>;
+type SharedBatch<K, V> = Shared<BoxFuture<'static, BatchOutcome<K, V>>>;
+
+struct OpenBatch<K, V> {
+    id: u64,
+    keys: Arc<Mutex<Vec<K>>>,
+    full: Arc<Notify>,
+    future: SharedBatch<K, V>,
+}
+
+struct State<K, V> {
+    next_id: u64,
+    open: Option<OpenBatch<K, V>>,
+    /// Batches waiting or being fetched, by the keys they look up
+    in_flight: HashMap<K, SharedBatch<K, V>, RandomState>,
+}
+impl<K, V> Default for State<K, V> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            open: None,
+            in_flight: HashMap::default(),
+        }
+    }
+}
+
+/// Coalesces single lookups made over a short window into batch requests
+///
+/// Lookups of a key already waiting or being fetched share its batch. The batch is driven
+/// by whichever of its callers is polled, so dropping any of them cancels nothing for the others.
+pub struct Batcher<K, V> {
+    fetch: Fetch<K, V>,
+    key_of: fn(&V) -> Option<K>,
+    max_batch_size: usize,
+    window: Duration,
+    state: Arc<Mutex<State<K, V>>>,
+}
+impl<K, V> Debug for Batcher<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batcher")
+            .field("max_batch_size", &self.max_batch_size)
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+impl<K, V> Batcher<K, V>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Batches lookups with `fetch`, matching the values it returns to their keys with `key_of`
+    #[must_use]
+    pub fn new<F, Fut>(fetch: F, key_of: fn(&V) -> Option<K>, max_batch_size: usize) -> Self
+    where
+        F: Fn(Vec<K>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = RequestResult<Vec<V>, JsonError>> + Send + 'static,
+    {
+        Self {
+            fetch: Arc::new(move |keys| fetch(keys).boxed()),
+            key_of,
+            max_batch_size: max_batch_size.max(1),
+            window: DEFAULT_WINDOW,
+            state: Arc::default(),
+        }
+    }
+    /// How long a batch waits for more lookups, unless it fills up before
+    #[must_use]
+    pub const fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+    /// The value for the key, `None` if the batch response did not include it
+    pub async fn get(&self, key: K) -> Result<Option<V>, BatchError> {
+        let batch = self.join(key.clone());
+        Ok(batch.await?.get(&key).cloned())
+    }
+    fn join(&self, key: K) -> SharedBatch<K, V> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(batch) = state.in_flight.get(&key) {
+            return batch.clone();
+        }
+        let open = state
+            .open
+            .take()
+            .unwrap_or_else(|| self.open_batch(&mut state.next_id));
+        let is_full = push_key(&open.keys, key.clone()) >= self.max_batch_size;
+        let batch = open.future.clone();
+        if is_full {
+            open.full.notify_one();
+        } else {
+            state.open = Some(open);
+        }
+        state.in_flight.insert(key, batch.clone());
+        drop(state);
+        batch
+    }
+    fn open_batch(&self, next_id: &mut u64) -> OpenBatch<K, V> {
+        let id = *next_id;
+        *next_id += 1;
+        let keys = Arc::new(Mutex::default());
+        let full = Arc::new(Notify::new());
+        let future = run_batch(
+            id,
+            keys.clone(),
+            full.clone(),
+            Arc::downgrade(&self.state),
+            self.fetch.clone(),
+            self.key_of,
+            self.window,
+        )
+        .boxed()
+        .shared();
+        OpenBatch {
+            id,
+            keys,
+            full,
+            future,
+        }
+    }
+}
+
+fn push_key<K>(keys: &Mutex<Vec<K>>, key: K) -> usize {
+    let mut keys = keys.lock().unwrap();
+    keys.push(key);
+    keys.len()
+}
+
+fn close_batch<K, V>(state: &Weak<Mutex<State<K, V>>>, id: u64) {
+    if let Some(state) = state.upgrade() {
+        let mut state = state.lock().unwrap();
+        if state.open.as_ref().is_some_and(|open| open.id == id) {
+            state.open = None;
+        }
+    }
+}
+
+fn finish_batch<K: Eq + Hash, V>(state: &Weak<Mutex<State<K, V>>>, keys: &[K]) {
+    if let Some(state) = state.upgrade() {
+        let mut state = state.lock().unwrap();
+        for key in keys {
+            state.in_flight.remove(key);
+        }
+    }
+}
+
+async fn run_batch<K: Clone + Eq + Hash + Send + Sync, V: Send + Sync>(
+    id: u64,
+    keys: Arc<Mutex<Vec<K>>>,
+    full: Arc<Notify>,
+    state: Weak<Mutex<State<K, V>>>,
+    fetch: Fetch<K, V>,
+    key_of: fn(&V) -> Option<K>,
+    window: Duration,
+) -> BatchOutcome<K, V> {
+    future::select(pin!(tokio::time::sleep(window)), pin!(full.notified())).await;
+    close_batch(&state, id);
+    let keys = mem::take(&mut *keys.lock().unwrap());
+    let result = fetch(keys.clone()).await;
+    finish_batch(&state, &keys);
+    result
+        .map(|values| {
+            Arc::new(
+                values
+                    .into_iter()
+                    .filter_map(|value| Some((key_of(&value)?, value)))
+                    .collect(),
+            )
+        })
+        .map_err(Arc::new)
+}
+
+impl Batcher<Id, BatchUserInfoFromId> {
+    /// Batches [`UsersApi::get_user_info_from_id_batch`], including banned users
+    #[must_use]
+    pub fn users<C: BaseClient + Send + 'static>(client: Arc<C>) -> Self {
+        Self::new(
+            move |users| {
+                let client = client.clone();
+                async move { client.get_user_info_from_id_batch(users, false).await }
+            },
+            |user| Some(user.id),
+            USERS_BATCH_LIMIT,
+        )
+    }
+}
+
+impl Batcher<Id, BatchGroupInfo> {
+    /// Batches [`GroupsApi::get_group_info_batch`]
+    #[must_use]
+    pub fn groups<C: BaseClient + Send + 'static>(client: Arc<C>) -> Self {
+        Self::new(
+            move |groups| {
+                let client = client.clone();
+                async move { client.get_group_info_batch(groups).await }
+            },
+            |group| Some(group.id),
+            GROUP_INFO_BATCH_LIMIT,
+        )
+    }
+}
+
+impl Batcher<Id, BatchThumbnailResult> {
+    /// Batches [`ThumbnailsApi::get_batch_thumbnails`] for targets sharing the same options
+    #[must_use]
+    pub fn thumbnails<C: BaseClient + Send + 'static>(
+        client: Arc<C>,
+        r#type: ThumbnailType,
+        size: ThumbnailSize,
+        format: ThumbnailFormat,
+    ) -> Self {
+        Self::new(
+            move |targets: Vec<Id>| {
+                let client = client.clone();
+                async move {
+                    client
+                        .get_batch_thumbnails(targets.into_iter().map(|target| BatchRequest::<
+                            (),
+                            (),
+                            (),
+                        > {
+                            target_id: target.into(),
+                            r#type,
+                            size,
+                            format,
+                            ..Default::default()
+                        }))
+                        .await
+                }
+            },
+            |thumbnail| thumbnail.target_id().get(),
+            BATCH_THUMBNAILS_LIMIT,
+        )
+    }
+}
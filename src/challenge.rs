@@ -0,0 +1,149 @@
+use std::{
+    fmt::{self, Debug, Display},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::{
+    RequestBuilder,
+    header::{HeaderMap, HeaderValue},
+};
+use serde::de::DeserializeOwned;
+
+pub(crate) const CHALLENGE_ID_HEADER: &str = "rblx-challenge-id";
+pub(crate) const CHALLENGE_TYPE_HEADER: &str = "rblx-challenge-type";
+pub(crate) const CHALLENGE_METADATA_HEADER: &str = "rblx-challenge-metadata";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChallengeType {
+    Captcha,
+    TwoStepVerification,
+    Reauthentication,
+    ProofOfWork,
+    Rostile,
+    PrivateAccessToken,
+    Generic,
+    Other(String),
+}
+impl ChallengeType {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Captcha => "captcha",
+            Self::TwoStepVerification => "twostepverification",
+            Self::Reauthentication => "reauthentication",
+            Self::ProofOfWork => "proofofwork",
+            Self::Rostile => "rostile",
+            Self::PrivateAccessToken => "privateaccesstoken",
+            Self::Generic => "generic",
+            Self::Other(value) => value,
+        }
+    }
+}
+impl From<&str> for ChallengeType {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "captcha" => Self::Captcha,
+            "twostepverification" => Self::TwoStepVerification,
+            "reauthentication" => Self::Reauthentication,
+            "proofofwork" => Self::ProofOfWork,
+            "rostile" => Self::Rostile,
+            "privateaccesstoken" => Self::PrivateAccessToken,
+            "generic" => Self::Generic,
+            _ => Self::Other(String::from(value)),
+        }
+    }
+}
+impl Display for ChallengeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Challenge sent through the `rblx-challenge-*` headers before an action is allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub id: String,
+    pub r#type: ChallengeType,
+    /// Json decoded from the base64 metadata header
+    pub metadata: String,
+}
+impl Challenge {
+    #[must_use]
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let metadata = header(CHALLENGE_METADATA_HEADER)
+            .and_then(|metadata| STANDARD.decode(metadata).ok())
+            .and_then(|metadata| String::from_utf8(metadata).ok())
+            .unwrap_or_default();
+        Some(Self {
+            id: String::from(header(CHALLENGE_ID_HEADER)?),
+            r#type: ChallengeType::from(header(CHALLENGE_TYPE_HEADER)?),
+            metadata,
+        })
+    }
+    pub fn metadata_as<T: DeserializeOwned>(&self) -> sonic_rs::Result<T> {
+        sonic_rs::from_str(&self.metadata)
+    }
+}
+impl Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.r#type, self.id)
+    }
+}
+
+/// Answer to a [`Challenge`], sent back in the headers of the resubmitted request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolvedChallenge {
+    pub id: String,
+    pub r#type: ChallengeType,
+    /// Json, base64 encoded when sent
+    pub metadata: String,
+}
+impl SolvedChallenge {
+    pub(crate) fn apply(&self, builder: RequestBuilder) -> RequestBuilder {
+        let metadata = STANDARD.encode(&self.metadata);
+        builder
+            .header(CHALLENGE_ID_HEADER, &self.id)
+            .header(CHALLENGE_TYPE_HEADER, self.r#type.as_str())
+            .header(
+                CHALLENGE_METADATA_HEADER,
+                HeaderValue::from_str(&metadata).unwrap(),
+            )
+    }
+}
+
+/// Solves challenges so that the client can resubmit challenged requests by itself
+#[async_trait]
+pub trait ChallengeSolver: Debug + Send + Sync {
+    /// None leaves the challenge unsolved, failing the request with
+    /// [`Error::ChallengeRequired`](crate::apis::Error::ChallengeRequired)
+    async fn solve(&self, challenge: &Challenge) -> Option<SolvedChallenge>;
+}
+
+/// Tries solvers in order until one of them solves the challenge
+#[derive(Debug, Clone)]
+pub struct ChainedSolver {
+    solvers: Vec<Arc<dyn ChallengeSolver>>,
+}
+impl ChainedSolver {
+    #[must_use]
+    pub fn new(solvers: impl IntoIterator<Item = Arc<dyn ChallengeSolver>>) -> Self {
+        Self {
+            solvers: solvers.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChallengeSolver for ChainedSolver {
+    async fn solve(&self, challenge: &Challenge) -> Option<SolvedChallenge> {
+        for solver in &self.solvers {
+            if let Some(solved) = solver.solve(challenge).await {
+                return Some(solved);
+            }
+        }
+        None
+    }
+}
@@ -1,67 +1,188 @@
-use ahash::RandomState;
 use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use bytes::Bytes;
-use dashmap::DashMap;
-use itertools::Itertools;
 use reqwest::{
-    Client as ReqwestClient, ClientBuilder as ReqwestClientBuilder, IntoUrl, Method,
-    RequestBuilder, Url, cookie::CookieStore, header::HeaderValue,
+    Client as ReqwestClient, ClientBuilder as ReqwestClientBuilder, IntoUrl, Method, Request,
+    RequestBuilder, Response, ResponseBuilderExt, StatusCode,
+    header::{HeaderMap, HeaderValue, InvalidHeaderValue},
 };
 use serde::{Serialize, de::DeserializeOwned};
-use std::sync::Arc;
+use std::{
+    fs, mem,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+use tokio::sync::{Mutex as AsyncMutex, watch};
 
-use crate::apis::{Error, RequestResult, RobloxError};
-use crate::{AuthenticatedClient, BaseClient};
+use crate::apis::two_step_verification::{Totp, TwoStepVerificationSolver};
+use crate::apis::{Error, JsonError, RequestResult, ResponseInfo, RobloxError, StringError};
+use crate::challenge::{ChainedSolver, Challenge, ChallengeSolver};
+use crate::endpoints::{Endpoints, Service};
+use crate::rate_limit::{Quota, RateLimiter};
+use crate::retry::{RetryPolicy, send_with_retry};
+use crate::{AuthenticatedClient, BaseClient, OpenCloudClient};
 
 pub use reqwest::ClientBuilder;
 pub use reqwest::Proxy;
 
+use auth::{ApiKeyAuth, Authenticated, Authenticator, CookieAuth, NoAuth};
+use jar::{JarError, JarFormat, StoredCookie};
+use middleware::{Middleware, Next};
+
+pub mod account_pool;
+pub mod auth;
+pub mod cache;
+pub mod cassette;
+pub mod jar;
+pub mod middleware;
+pub mod oauth;
+pub mod proxy_pool;
+
 const CSRF_TOKEN_HEADER: &str = "x-csrf-token";
-const AUTHENTICATION_COOKIE_NAME: &str = ".roblosecurity";
+const TOKEN_VALIDATION_FAILED: &str = "Token Validation Failed";
+pub(crate) const AUTHENTICATION_COOKIE_NAME: &str = ".ROBLOSECURITY";
+/// Challenges solved for one request before giving up, as they can be chained
+const MAX_CHALLENGE_ROUNDS: usize = 3;
+
+/// Used by the clients which do not download through their own transport
+pub(crate) static DEFAULT_CLIENT: LazyLock<Client> =
+    LazyLock::new(|| Client::new(ReqwestClientBuilder::new()));
+
+pub type Client = HttpClient<NoAuth>;
+pub type CookieClient = HttpClient<CookieAuth>;
+pub type ApiKeyClient = HttpClient<ApiKeyAuth>;
+
+macro_rules! impl_base_client {
+    ($client: ty) => {
+        #[async_trait]
+        impl BaseClient for $client {
+            #[inline]
+            fn endpoints(&self) -> &Endpoints {
+                &self.endpoints
+            }
+            #[inline]
+            async fn acquire_quota(&self, quota: Quota) {
+                self.acquire_quota(quota).await;
+            }
+            async fn request<T: DeserializeOwned, E: RobloxError>(
+                &self,
+                method: Method,
+                url: impl IntoUrl + Send,
+                query: Option<impl Serialize + Send>,
+                payload: Option<impl Serialize + Send>,
+            ) -> RequestResult<T, E> {
+                self.request(method, url, query, payload).await
+            }
+            #[inline]
+            async fn download(
+                &self,
+                url: impl IntoUrl + Send,
+            ) -> RequestResult<Bytes, StringError> {
+                self.download(url).await
+            }
+        }
+    };
+}
+
+/// Csrf token shared by the clones of a client
+#[derive(Debug, Default)]
+struct CsrfToken {
+    token: ArcSwapOption<HeaderValue>,
+    /// Held while the token is fetched or replaced
+    refresh: AsyncMutex<()>,
+}
 
-#[derive(Default, Debug)]
-struct StaticSharedJar(DashMap<String, String, RandomState>);
-impl StaticSharedJar {
-    fn new() -> Self {
-        Self::default()
+/// Http transport shared by every client, parameterized by how requests are authenticated
+#[derive(Debug, Default)]
+pub struct HttpClient<A: Authenticator = NoAuth> {
+    http: ReqwestClient,
+    csrf: Arc<CsrfToken>,
+    endpoints: Arc<Endpoints>,
+    retry_policy: Option<RetryPolicy>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    rate_limit_partition: Option<Arc<str>>,
+    challenge_solver: Option<Arc<dyn ChallengeSolver>>,
+    middlewares: Arc<Vec<Arc<dyn Middleware>>>,
+    auth: Arc<A>,
+}
+impl<A: Authenticator> Clone for HttpClient<A> {
+    fn clone(&self) -> Self {
+        Self {
+            http: self.http.clone(),
+            csrf: self.csrf.clone(),
+            endpoints: self.endpoints.clone(),
+            retry_policy: self.retry_policy,
+            rate_limiter: self.rate_limiter.clone(),
+            rate_limit_partition: self.rate_limit_partition.clone(),
+            challenge_solver: self.challenge_solver.clone(),
+            middlewares: self.middlewares.clone(),
+            auth: self.auth.clone(),
+        }
     }
-    fn insert(&self, name: &str, value: &str) {
-        self.0.insert(String::from(name), String::from(value));
+}
+impl<A: Authenticator> HttpClient<A> {
+    #[must_use]
+    pub fn with_authenticator(builder: ReqwestClientBuilder, auth: A) -> Self {
+        Self {
+            http: auth.configure(builder).build().unwrap(),
+            csrf: Arc::default(),
+            endpoints: Arc::default(),
+            retry_policy: None,
+            rate_limiter: None,
+            rate_limit_partition: None,
+            challenge_solver: None,
+            middlewares: Arc::default(),
+            auth: Arc::new(auth),
+        }
     }
-    fn remove(&self, name: &str) {
-        self.0.remove(name);
+    #[inline]
+    #[must_use]
+    pub fn authenticator(&self) -> &A {
+        &self.auth
     }
-    fn get(&self, name: &str) -> Option<String> {
-        self.0.get(name).map(|x| x.clone())
+    #[must_use]
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = Arc::new(endpoints);
+        self
     }
-    fn clear(&self) {
-        self.0.clear();
+    #[must_use]
+    pub const fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
     }
-}
-impl CookieStore for StaticSharedJar {
-    fn cookies(&self, _url: &Url) -> Option<HeaderValue> {
-        let cookie_string = self
-            .0
-            .iter()
-            .map(|x| format!("{}={}", x.key(), x.value()))
-            .join("; ");
-
-        if cookie_string.is_empty() {
-            None
-        } else {
-            HeaderValue::from_maybe_shared(Bytes::from(cookie_string)).ok()
+    /// Makes requests wait for the documented endpoint limits, see [`RateLimiter`]
+    #[must_use]
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+    /// Counts this client's requests separately from other clients sharing the same limiter
+    #[must_use]
+    pub fn with_rate_limit_partition(mut self, partition: &str) -> Self {
+        self.rate_limit_partition = Some(Arc::from(partition));
+        self
+    }
+    /// Resubmits requests answered with a challenge once the solver solves it
+    ///
+    /// Replaces the solver set before, use a [`ChainedSolver`] to keep several.
+    #[must_use]
+    pub fn with_challenge_solver(mut self, solver: Arc<dyn ChallengeSolver>) -> Self {
+        self.challenge_solver = Some(solver);
+        self
+    }
+    /// Adds a middleware after those already set, see [`middleware::MiddlewareClientBuilder`]
+    #[must_use]
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        Arc::make_mut(&mut self.middlewares).push(Arc::new(middleware));
+        self
+    }
+    pub async fn acquire_quota(&self, quota: Quota) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter
+                .acquire(&quota, self.rate_limit_partition.as_deref())
+                .await;
         }
     }
-    fn set_cookies(&self, _cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, _url: &Url) {}
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct Client {
-    client: ReqwestClient,
-    csrf_token: Arc<ArcSwapOption<HeaderValue>>,
-}
-impl Client {
     fn build_request(
         &self,
         method: Method,
@@ -71,7 +192,7 @@ impl Client {
         csrf_token: Option<&HeaderValue>,
     ) -> RequestBuilder {
         let is_get = matches!(method, Method::GET);
-        let mut builder = self.client.request(method, url);
+        let mut builder = self.auth.authenticate(self.http.request(method, url));
         if let Some(query) = query {
             builder = builder.query(&query);
         }
@@ -82,10 +203,10 @@ impl Client {
                 .header("Content-Length", 0)
                 .header("Content-Type", "application/json"),
         };
-        if let Some(csrf_token) = csrf_token {
-            if !is_get {
-                builder = builder.header(CSRF_TOKEN_HEADER, csrf_token);
-            }
+        if let Some(csrf_token) = csrf_token
+            && !is_get
+        {
+            builder = builder.header(CSRF_TOKEN_HEADER, csrf_token);
         }
         builder
     }
@@ -96,130 +217,357 @@ impl Client {
         query: Option<impl Serialize + Send>,
         payload: Option<impl Serialize + Send>,
     ) -> RequestResult<T, E> {
-        let old_csrf_token = self.csrf_token.load();
-        let builder = self.build_request(method, url, query, payload, old_csrf_token.as_deref());
-        let mut response = builder.try_clone().unwrap().send().await?;
-        if let Some(csrf_token) = response.headers().get(CSRF_TOKEN_HEADER) {
-            self.csrf_token.store(Some(Arc::new(csrf_token.to_owned())));
-            response = builder.header(CSRF_TOKEN_HEADER, csrf_token).send().await?;
-        }
-        if response.status() == 429 {
-            return Err(Error::RateLimit);
-        }
-        let res = response.text().await?;
-        sonic_rs::from_str::<T>(&res).map_or_else(|_| Err(E::parse(res).into()), |value| Ok(value))
+        let csrf_token = if method == Method::GET || !self.auth.uses_csrf_token() {
+            None
+        } else {
+            self.csrf_token().await
+        };
+        let builder =
+            self.build_request(method.clone(), url, query, payload, csrf_token.as_deref());
+        self.execute(&method, builder).await
     }
-    #[must_use]
-    pub fn new(builder: ReqwestClientBuilder) -> Self {
-        Self {
-            client: builder.build().unwrap(),
-            csrf_token: Arc::new(ArcSwapOption::const_empty()),
+    /// Fetches a raw body, like an image from the cdn, without authenticating the request
+    ///
+    /// It goes through the same transport as the other requests, the jar only sending cookies
+    /// to the domains they are scoped to.
+    pub async fn download(&self, url: impl IntoUrl + Send) -> RequestResult<Bytes, StringError> {
+        let response = self.send(&Method::GET, &self.http.get(url)).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = response.bytes().await?;
+        if !status.is_success() {
+            return Err(response_error(
+                status,
+                &headers,
+                &String::from_utf8_lossy(&bytes),
+            ));
         }
+        Ok(bytes)
     }
-}
-
-#[async_trait]
-impl BaseClient for Client {
-    async fn request<T: DeserializeOwned, E: RobloxError>(
+    /// Sends a form encoded body, as expected by the `OAuth2` endpoints
+    ///
+    /// The credentials go in the form, so the request is not authenticated.
+    pub(crate) async fn request_form<T: DeserializeOwned, E: RobloxError>(
         &self,
-        method: Method,
         url: impl IntoUrl + Send,
-        query: Option<impl Serialize + Send>,
-        payload: Option<impl Serialize + Send>,
+        form: &(impl Serialize + Sync),
     ) -> RequestResult<T, E> {
-        self.request(method, url, query, payload).await
+        let builder = self.http.request(Method::POST, url).form(form);
+        self.execute(&Method::POST, builder).await
     }
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct CookieClient {
-    client: ReqwestClient,
-    csrf_token: Arc<ArcSwapOption<HeaderValue>>,
-    jar: Arc<StaticSharedJar>,
-}
-impl CookieClient {
-    fn build_request(
-        &self,
-        method: Method,
-        url: impl IntoUrl + Send,
-        query: Option<impl Serialize>,
-        payload: Option<impl Serialize>,
-        csrf_token: Option<&HeaderValue>,
-    ) -> RequestBuilder {
-        let is_get = matches!(method, Method::GET);
-        let mut builder = self.client.request(method, url);
-        if let Some(query) = query {
-            builder = builder.query(&query);
+    /// The current csrf token, fetched before the first request needing one until it is got
+    async fn csrf_token(&self) -> Option<Arc<HeaderValue>> {
+        if let Some(csrf_token) = self.csrf.token.load_full() {
+            return Some(csrf_token);
         }
-        builder = match payload {
-            Some(payload) => builder.json(&payload),
-            None => builder
-                .body("")
-                .header("Content-Length", 0)
-                .header("Content-Type", "application/json"),
+        let refresh = self.csrf.refresh.lock().await;
+        // Another request may have fetched it while this one waited
+        if self.csrf.token.load().is_none() {
+            self.fetch_csrf_token().await;
+        }
+        drop(refresh);
+        self.csrf.token.load_full()
+    }
+    /// Logging out without a csrf token only fails, answering with a fresh one
+    async fn fetch_csrf_token(&self) {
+        let url = format!("{}v2/logout", self.endpoints.get(Service::Auth));
+        let builder = self
+            .auth
+            .authenticate(self.http.post(url))
+            .header("Content-Length", 0);
+        let response = match builder.build() {
+            Ok(request) => self.dispatch(request).await,
+            Err(error) => Err(error),
         };
-        if let Some(csrf_token) = csrf_token {
-            if !is_get {
-                builder = builder.header(CSRF_TOKEN_HEADER, csrf_token);
+        match response {
+            Ok(response) => {
+                if let Some(csrf_token) = response.headers().get(CSRF_TOKEN_HEADER) {
+                    self.csrf.token.store(Some(Arc::new(csrf_token.clone())));
+                }
             }
+            Err(error) => tracing::debug!(%error, "failed to fetch csrf token"),
         }
-        builder
     }
-    pub async fn request<T: DeserializeOwned, E: RobloxError>(
+    /// Keeps the token another request already replaced the stale one with, or stores the
+    /// offered one, so concurrent failures share a single refresh
+    async fn refresh_csrf_token(
         &self,
-        method: Method,
-        url: impl IntoUrl + Send,
-        query: Option<impl Serialize + Send>,
-        payload: Option<impl Serialize + Send>,
+        stale: Option<&HeaderValue>,
+        offered: HeaderValue,
+    ) -> HeaderValue {
+        let refresh = self.csrf.refresh.lock().await;
+        let csrf_token = match self.csrf.token.load_full() {
+            Some(current) if Some(current.as_ref()) != stale => current.as_ref().clone(),
+            _ => {
+                self.csrf.token.store(Some(Arc::new(offered.clone())));
+                offered
+            }
+        };
+        drop(refresh);
+        csrf_token
+    }
+    async fn dispatch(&self, request: Request) -> reqwest::Result<Response> {
+        Next::new(&self.http, &self.middlewares).run(request).await
+    }
+    /// Sends the request, resending it once if its csrf token was rejected
+    ///
+    /// Only a 403 "Token Validation Failed" is resent, as the request was not processed.
+    async fn send(&self, method: &Method, builder: &RequestBuilder) -> reqwest::Result<Response> {
+        send_with_retry(self.retry_policy.as_ref(), method, || async {
+            let request = builder.try_clone().unwrap().build()?;
+            let stale = request.headers().get(CSRF_TOKEN_HEADER).cloned();
+            let response = self.dispatch(request).await?;
+            let Some(offered) = response.headers().get(CSRF_TOKEN_HEADER).cloned() else {
+                return Ok(response);
+            };
+            if response.status() != StatusCode::FORBIDDEN {
+                return Ok(response);
+            }
+            let (is_token_failure, response) = check_token_validation(response).await?;
+            if !is_token_failure {
+                return Ok(response);
+            }
+            let csrf_token = self.refresh_csrf_token(stale.as_ref(), offered).await;
+            let mut request = builder.try_clone().unwrap().build()?;
+            request.headers_mut().insert(CSRF_TOKEN_HEADER, csrf_token);
+            self.dispatch(request).await
+        })
+        .await
+    }
+    async fn execute<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        method: &Method,
+        builder: RequestBuilder,
     ) -> RequestResult<T, E> {
-        let old_csrf_token = self.csrf_token.load();
-        let builder = self.build_request(method, url, query, payload, old_csrf_token.as_deref());
-        let mut response = builder.try_clone().unwrap().send().await?;
-        if let Some(csrf_token) = response.headers().get(CSRF_TOKEN_HEADER) {
-            self.csrf_token.swap(Some(Arc::new(csrf_token.to_owned())));
-            response = builder.header(CSRF_TOKEN_HEADER, csrf_token).send().await?;
-        }
-        if response.status() == 429 {
-            return Err(Error::RateLimit);
+        let mut response = self.send(method, &builder).await?;
+        if let Some(solver) = &self.challenge_solver {
+            for _ in 0..MAX_CHALLENGE_ROUNDS {
+                if response.status().is_success() {
+                    break;
+                }
+                let Some(challenge) = Challenge::from_headers(response.headers()) else {
+                    break;
+                };
+                let Some(solved) = solver.solve(&challenge).await else {
+                    break;
+                };
+                response = self
+                    .send(method, &solved.apply(builder.try_clone().unwrap()))
+                    .await?;
+            }
         }
+        let status = response.status();
+        let headers = response.headers().clone();
         let res = response.text().await?;
-        sonic_rs::from_str::<T>(&res).map_or_else(|_| Err(E::parse(res).into()), |value| Ok(value))
+        decode_response(status, &headers, &res)
+    }
+}
+
+/// Reads the body of a 403 to tell csrf failures apart, giving back an equivalent response
+///
+/// The rebuilt response keeps the url and extensions of the original one.
+async fn check_token_validation(mut response: Response) -> reqwest::Result<(bool, Response)> {
+    let mut rebuilt = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    *rebuilt.headers_mut().unwrap() = mem::take(response.headers_mut());
+    *rebuilt.extensions_mut().unwrap() = mem::take(response.extensions_mut());
+    let rebuilt = rebuilt.url(response.url().clone());
+    let body = response.bytes().await?;
+    let is_token_failure = JsonError::parse(String::from_utf8_lossy(&body).into_owned())
+        .errors()
+        .iter()
+        .any(|error| error.message == TOKEN_VALIDATION_FAILED);
+    Ok((
+        is_token_failure,
+        Response::from(rebuilt.body(body).unwrap()),
+    ))
+}
+
+/// Turns a response into the expected value or the matching [`Error`] variant
+pub(crate) fn decode_response<T: DeserializeOwned, E: RobloxError>(
+    status: StatusCode,
+    headers: &HeaderMap,
+    res: &str,
+) -> RequestResult<T, E> {
+    if !status.is_success() {
+        return Err(response_error(status, headers, res));
+    }
+    let body = if res.is_empty() { "null" } else { res };
+    if let Ok(value) = sonic_rs::from_str::<T>(body) {
+        return Ok(value);
+    }
+    // Some endpoints answer errors with a success status
+    let error = E::parse(String::from(res));
+    if error.is_structured() {
+        return Err(Error::Api {
+            error,
+            response: Some(Box::new(ResponseInfo::new(
+                status,
+                headers,
+                String::from(res),
+            ))),
+        });
+    }
+    let mut deserializer = sonic_rs::Deserializer::from_str(body);
+    match serde_path_to_error::deserialize::<_, T>(&mut deserializer) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(Error::Decode {
+            path: error.path().to_string(),
+            message: error.into_inner().to_string(),
+            response: Box::new(ResponseInfo::new(status, headers, String::from(res))),
+        }),
+    }
+}
+
+/// The [`Error`] variant matching an unsuccessful response
+fn response_error<E: RobloxError>(status: StatusCode, headers: &HeaderMap, res: &str) -> Error<E> {
+    let info = || Box::new(ResponseInfo::new(status, headers, String::from(res)));
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return Error::RateLimit(Some(info()));
     }
+    if status == StatusCode::UNAUTHORIZED {
+        let error = E::parse(String::from(res));
+        return if error.is_structured() {
+            Error::Api {
+                error,
+                response: Some(info()),
+            }
+        } else {
+            Error::Unauthorized(Some(info()))
+        };
+    }
+    if let Some(challenge) = Challenge::from_headers(headers) {
+        return Error::ChallengeRequired {
+            challenge: Box::new(challenge),
+            error: E::parse(String::from(res)),
+            response: info(),
+        };
+    }
+    Error::Api {
+        error: E::parse(String::from(res)),
+        response: Some(info()),
+    }
+}
+
+impl Client {
+    #[must_use]
+    pub fn new(builder: ReqwestClientBuilder) -> Self {
+        Self::with_authenticator(builder, NoAuth)
+    }
+}
+
+impl_base_client!(Client);
+
+impl CookieClient {
     #[must_use]
     pub fn new(builder: ReqwestClientBuilder, auth_cookie: &str) -> Self {
-        let jar = Arc::new(StaticSharedJar::new());
-        jar.insert(AUTHENTICATION_COOKIE_NAME, auth_cookie);
-        Self {
-            client: builder.cookie_provider(jar.clone()).build().unwrap(),
-            csrf_token: Arc::new(ArcSwapOption::const_empty()),
-            jar,
-        }
+        Self::with_authenticator(builder, CookieAuth::new(auth_cookie))
+    }
+    /// Loads every cookie from a file, see [`JarFormat::from_path`] for the format used
+    pub fn from_jar_file(
+        builder: ReqwestClientBuilder,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, JarError> {
+        let client = Self::with_authenticator(builder, CookieAuth::default());
+        client.load_jar_file(path)?;
+        Ok(client)
+    }
+    pub fn load_jar_file(&self, path: impl AsRef<Path>) -> Result<(), JarError> {
+        let path = path.as_ref();
+        self.import_jar(&fs::read_to_string(path)?, JarFormat::from_path(path))
+    }
+    pub fn save_jar_file(&self, path: impl AsRef<Path>) -> Result<(), JarError> {
+        let path = path.as_ref();
+        fs::write(path, self.export_jar(JarFormat::from_path(path)))?;
+        Ok(())
+    }
+    pub fn import_jar(&self, data: &str, format: JarFormat) -> Result<(), JarError> {
+        self.auth.jar.import(data, format)
+    }
+    #[must_use]
+    pub fn export_jar(&self, format: JarFormat) -> String {
+        self.auth.jar.export(format)
     }
     #[inline]
     pub fn insert_cookie(&self, name: &str, value: &str) {
-        self.jar.insert(name, value);
+        self.auth.jar.insert(name, value);
     }
     #[inline]
     pub fn remove_cookie(&self, name: &str) {
-        self.jar.remove(name);
+        self.auth.jar.remove(name);
     }
     #[inline]
     #[must_use]
     pub fn get_cookie(&self, name: &str) -> Option<String> {
-        self.jar.get(name)
+        self.auth.jar.get(name)
     }
     #[inline]
     pub fn clear_cookies(&self) {
-        self.jar.clear();
+        self.auth.jar.clear();
+    }
+    /// The cookie with its domain and expiry, as last set by a response or by hand
+    #[inline]
+    #[must_use]
+    pub fn get_stored_cookie(&self, name: &str) -> Option<StoredCookie> {
+        self.auth.jar.get_cookie(name)
     }
     #[inline]
     pub fn set_auth_cookie(&self, cookie: &str) {
-        self.jar.insert(".ROBLOSECURITY", cookie);
+        self.auth.jar.insert(AUTHENTICATION_COOKIE_NAME, cookie);
+    }
+    #[inline]
+    #[must_use]
+    pub fn get_auth_cookie(&self) -> Option<String> {
+        self.auth.jar.get(AUTHENTICATION_COOKIE_NAME)
+    }
+    /// Receives the new `.ROBLOSECURITY` value whenever it changes, e.g. when Roblox rotates it
+    #[inline]
+    #[must_use]
+    pub fn watch_auth_cookie(&self) -> watch::Receiver<Option<String>> {
+        self.auth.jar.subscribe()
+    }
+    /// Passes two step verification challenges with codes generated from the authenticator secret
+    ///
+    /// Other challenges still go to the solver set before, if any.
+    #[must_use]
+    pub fn with_two_step_verification(self, totp: Totp) -> Self {
+        let two_step: Arc<dyn ChallengeSolver> =
+            Arc::new(TwoStepVerificationSolver::new(self.clone(), totp));
+        let solver = match &self.challenge_solver {
+            Some(solver) => Arc::new(ChainedSolver::new([two_step, solver.clone()])),
+            None => two_step,
+        };
+        self.with_challenge_solver(solver)
+    }
+}
+
+impl ApiKeyClient {
+    /// Fails if the key has characters not allowed in a header
+    pub fn new(builder: ReqwestClientBuilder, api_key: &str) -> Result<Self, InvalidHeaderValue> {
+        Ok(Self::with_authenticator(builder, ApiKeyAuth::new(api_key)?))
+    }
+}
+
+impl_base_client!(ApiKeyClient);
+
+impl OpenCloudClient for ApiKeyClient {
+    #[inline]
+    fn has_scope(&self, scope: &str) -> bool {
+        self.auth.has_scope(scope)
     }
 }
 
 #[async_trait]
-impl AuthenticatedClient for CookieClient {
+impl<A: Authenticated> AuthenticatedClient for HttpClient<A> {
+    #[inline]
+    fn authenticated_endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+    #[inline]
+    async fn authenticated_acquire_quota(&self, quota: Quota) {
+        self.acquire_quota(quota).await;
+    }
     #[inline]
     async fn authenticated_request<T: DeserializeOwned, E: RobloxError>(
         &self,
@@ -230,4 +578,45 @@ impl AuthenticatedClient for CookieClient {
     ) -> RequestResult<T, E> {
         self.request(method, url, query, payload).await
     }
+    #[inline]
+    async fn authenticated_download(
+        &self,
+        url: impl IntoUrl + Send,
+    ) -> RequestResult<Bytes, StringError> {
+        self.download(url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{StatusCode, header::HeaderMap};
+
+    use super::decode_response;
+    use crate::apis::{Error, JsonError, RequestResult, StringError};
+
+    const ERRORS: &str =
+        r#"{"errors":[{"code":1,"message":"The group is invalid or does not exist."}]}"#;
+
+    #[test]
+    fn successful_error_envelope_is_an_api_error() {
+        let result: RequestResult<u64, JsonError> =
+            decode_response(StatusCode::OK, &HeaderMap::new(), ERRORS);
+        match result {
+            Err(Error::Api { error, response }) => {
+                assert_eq!(error.code(), Some(1));
+                assert_eq!(response.unwrap().status, StatusCode::OK);
+            }
+            result => panic!("expected an api error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn unexpected_body_is_a_decode_error() {
+        let result: RequestResult<u64, StringError> =
+            decode_response(StatusCode::OK, &HeaderMap::new(), ERRORS);
+        assert!(matches!(result, Err(Error::Decode { .. })));
+        let result: RequestResult<u64, JsonError> =
+            decode_response(StatusCode::OK, &HeaderMap::new(), r#"{"data":[]}"#);
+        assert!(matches!(result, Err(Error::Decode { .. })));
+    }
 }
@@ -0,0 +1,274 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{IntoUrl, Method};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    AuthenticatedClient,
+    apis::{Error, RequestResult, RobloxError, StringError, users::UsersAuthenticatedApi},
+    clients::cassette::request_with,
+    endpoints::Endpoints,
+    rate_limit::Quota,
+};
+
+use super::CookieClient;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    RoundRobin,
+    LeastRecentlyUsed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountPoolConfig {
+    pub rotation: Rotation,
+    /// Consecutive rate limits after which an account is benched
+    pub max_rate_limits: u32,
+    pub rate_limit_cooldown: Duration,
+    pub revalidate_interval: Duration,
+}
+impl Default for AccountPoolConfig {
+    fn default() -> Self {
+        Self {
+            rotation: Rotation::default(),
+            max_rate_limits: 3,
+            rate_limit_cooldown: Duration::from_secs(30),
+            revalidate_interval: Duration::from_mins(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountHealth {
+    Healthy,
+    /// Hit too many rate limits in a row, back in rotation at the given instant
+    RateLimited(Instant),
+    /// Its session was rejected, back in rotation once it passes revalidation
+    Unauthorized,
+}
+
+#[derive(Debug)]
+struct AccountState {
+    health: AccountHealth,
+    last_used: Option<Instant>,
+    consecutive_rate_limits: u32,
+}
+impl AccountState {
+    fn is_available(&mut self, now: Instant) -> bool {
+        match self.health {
+            AccountHealth::Healthy => true,
+            AccountHealth::RateLimited(until) if until <= now => {
+                self.health = AccountHealth::Healthy;
+                self.consecutive_rate_limits = 0;
+                true
+            }
+            AccountHealth::RateLimited(_) | AccountHealth::Unauthorized => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Account<C> {
+    client: C,
+    state: Mutex<AccountState>,
+}
+
+/// Spreads authenticated requests across accounts, taking unhealthy ones out of rotation
+///
+/// Quotas are taken through the account each request is sent with, so that accounts with
+/// their own rate limit partition are each charged for their own requests.
+#[derive(Debug)]
+pub struct AccountPool<C: AuthenticatedClient = CookieClient> {
+    accounts: Vec<Account<C>>,
+    next: AtomicUsize,
+    config: AccountPoolConfig,
+}
+impl<C: AuthenticatedClient> AccountPool<C> {
+    #[must_use]
+    pub fn new(clients: impl IntoIterator<Item = C>, config: AccountPoolConfig) -> Self {
+        let accounts = clients
+            .into_iter()
+            .map(|client| Account {
+                client,
+                state: Mutex::new(AccountState {
+                    health: AccountHealth::Healthy,
+                    last_used: None,
+                    consecutive_rate_limits: 0,
+                }),
+            })
+            .collect::<Vec<_>>();
+        assert!(
+            !accounts.is_empty(),
+            "account pool needs at least one account"
+        );
+        Self {
+            accounts,
+            next: AtomicUsize::new(0),
+            config,
+        }
+    }
+    pub fn clients(&self) -> impl Iterator<Item = &C> {
+        self.accounts.iter().map(|account| &account.client)
+    }
+    pub fn health(&self) -> impl Iterator<Item = AccountHealth> {
+        self.accounts
+            .iter()
+            .map(|account| account.state.lock().unwrap().health)
+    }
+    fn select(&self) -> Result<usize, Option<Instant>> {
+        let now = Instant::now();
+        let len = self.accounts.len();
+        let selected = match self.config.rotation {
+            Rotation::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..len).map(|offset| (start + offset) % len).find(|index| {
+                    self.accounts[*index]
+                        .state
+                        .lock()
+                        .unwrap()
+                        .is_available(now)
+                })
+            }
+            Rotation::LeastRecentlyUsed => self
+                .accounts
+                .iter()
+                .enumerate()
+                .filter_map(|(index, account)| {
+                    let mut state = account.state.lock().unwrap();
+                    state.is_available(now).then_some((index, state.last_used))
+                })
+                .min_by_key(|(_, last_used)| *last_used)
+                .map(|(index, _)| index),
+        };
+        selected
+            .inspect(|index| self.accounts[*index].state.lock().unwrap().last_used = Some(now))
+            .ok_or_else(|| {
+                self.accounts
+                    .iter()
+                    .filter_map(|account| {
+                        let health = account.state.lock().unwrap().health;
+                        match health {
+                            AccountHealth::RateLimited(until) => Some(until),
+                            _ => None,
+                        }
+                    })
+                    .min()
+            })
+    }
+    /// Waits for an account to come back from a rate limit, fails if all are unauthorized
+    async fn next_account<E: RobloxError>(&self) -> RequestResult<&Account<C>, E> {
+        loop {
+            match self.select() {
+                Ok(index) => return Ok(&self.accounts[index]),
+                Err(Some(until)) => tokio::time::sleep_until(until.into()).await,
+                Err(None) => return Err(Error::Unauthorized(None)),
+            }
+        }
+    }
+    /// Sends the request with the next account, taking the quota through that same account
+    async fn request_through_next<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Option<Quota>,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        let account = self.next_account().await?;
+        let result = request_with(&account.client, quota, method, url, query, payload).await;
+        self.record(account, &result);
+        result
+    }
+    fn record<T, E: RobloxError>(&self, account: &Account<C>, result: &RequestResult<T, E>) {
+        let mut state = account.state.lock().unwrap();
+        match result {
+            Err(error) if error.is_session_invalid() => {
+                tracing::warn!("taking unauthorized account out of rotation");
+                state.health = AccountHealth::Unauthorized;
+            }
+            Err(Error::RateLimit(_)) => {
+                state.consecutive_rate_limits += 1;
+                if state.consecutive_rate_limits >= self.config.max_rate_limits {
+                    tracing::warn!(cooldown = ?self.config.rate_limit_cooldown, "benching rate limited account");
+                    state.health = AccountHealth::RateLimited(
+                        Instant::now() + self.config.rate_limit_cooldown,
+                    );
+                }
+            }
+            _ => state.consecutive_rate_limits = 0,
+        }
+    }
+    /// Checks every account against the authenticated user endpoint, restoring those that pass
+    pub async fn revalidate(&self) {
+        for account in &self.accounts {
+            let result = account.client.get_authenticated().await;
+            let mut state = account.state.lock().unwrap();
+            match result {
+                Ok(_) => {
+                    if state.health == AccountHealth::Unauthorized {
+                        tracing::info!("restoring revalidated account");
+                        state.health = AccountHealth::Healthy;
+                    }
+                }
+                Err(error) if error.is_session_invalid() => {
+                    state.health = AccountHealth::Unauthorized;
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    /// Revalidates the accounts every [`AccountPoolConfig::revalidate_interval`], never returns
+    pub async fn revalidate_periodically(&self) {
+        loop {
+            tokio::time::sleep(self.config.revalidate_interval).await;
+            self.revalidate().await;
+        }
+    }
+}
+
+#[async_trait]
+impl<C: AuthenticatedClient + Send> AuthenticatedClient for AccountPool<C> {
+    #[inline]
+    fn authenticated_endpoints(&self) -> &Endpoints {
+        self.accounts[0].client.authenticated_endpoints()
+    }
+    async fn authenticated_request<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.request_through_next(None, method, url, query, payload)
+            .await
+    }
+    /// Takes the quota through the account the request is sent with
+    async fn authenticated_request_with_quota<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Quota,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.request_through_next(Some(quota), method, url, query, payload)
+            .await
+    }
+    /// Downloads are not authenticated, so they go through the first account
+    #[inline]
+    async fn authenticated_download(
+        &self,
+        url: impl IntoUrl + Send,
+    ) -> RequestResult<Bytes, StringError> {
+        self.accounts[0].client.authenticated_download(url).await
+    }
+}
@@ -0,0 +1,149 @@
+use std::{fmt::Debug, sync::Arc};
+
+use arc_swap::ArcSwap;
+use reqwest::{
+    ClientBuilder as ReqwestClientBuilder, RequestBuilder,
+    header::{AUTHORIZATION, HeaderValue, InvalidHeaderValue},
+};
+
+use super::jar::StaticSharedJar;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Attaches credentials to the requests of an [`HttpClient`](super::HttpClient)
+pub trait Authenticator: Debug + Send + Sync {
+    /// Called once when the underlying reqwest client is built
+    #[inline]
+    fn configure(&self, builder: ReqwestClientBuilder) -> ReqwestClientBuilder {
+        builder
+    }
+    /// Called for every request before it is sent
+    #[inline]
+    fn authenticate(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder
+    }
+    /// Whether a csrf token is fetched ahead of the first request other than GET
+    ///
+    /// Open Cloud credentials are not checked against csrf tokens, and the endpoints called
+    /// without credentials do not need one, so they skip it.
+    #[inline]
+    fn uses_csrf_token(&self) -> bool {
+        true
+    }
+}
+
+/// Marker for authenticators whose clients can call the authenticated apis
+pub trait Authenticated: Authenticator {}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoAuth;
+impl Authenticator for NoAuth {
+    #[inline]
+    fn uses_csrf_token(&self) -> bool {
+        false
+    }
+}
+
+/// Authenticates with the `.ROBLOSECURITY` cookie
+#[derive(Debug, Default)]
+pub struct CookieAuth {
+    pub(crate) jar: Arc<StaticSharedJar>,
+}
+impl CookieAuth {
+    #[must_use]
+    pub fn new(auth_cookie: &str) -> Self {
+        let jar = Arc::new(StaticSharedJar::new());
+        jar.insert(super::AUTHENTICATION_COOKIE_NAME, auth_cookie);
+        Self { jar }
+    }
+}
+impl Authenticator for CookieAuth {
+    fn configure(&self, builder: ReqwestClientBuilder) -> ReqwestClientBuilder {
+        builder.cookie_provider(self.jar.clone())
+    }
+}
+impl Authenticated for CookieAuth {}
+
+/// Authenticates with an Open Cloud api key
+///
+/// When the scopes granted to the key are declared, requests needing any other scope fail
+/// before being sent.
+#[derive(Debug)]
+pub struct ApiKeyAuth {
+    api_key: HeaderValue,
+    scopes: Option<Vec<String>>,
+}
+impl ApiKeyAuth {
+    /// Fails if the key has characters not allowed in a header
+    pub fn new(api_key: &str) -> Result<Self, InvalidHeaderValue> {
+        let mut api_key = HeaderValue::from_str(api_key)?;
+        api_key.set_sensitive(true);
+        Ok(Self {
+            api_key,
+            scopes: None,
+        })
+    }
+    #[must_use]
+    pub fn with_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = Some(scopes.into_iter().map(Into::into).collect());
+        self
+    }
+    /// Whether the key may grant the scope, always true if no scopes were declared
+    #[must_use]
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|granted| granted == scope))
+    }
+}
+impl Authenticator for ApiKeyAuth {
+    fn authenticate(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder.header(API_KEY_HEADER, self.api_key.clone())
+    }
+    #[inline]
+    fn uses_csrf_token(&self) -> bool {
+        false
+    }
+}
+
+/// Authenticates with an `OAuth2` bearer token, which can be swapped when it is refreshed
+#[derive(Debug)]
+pub struct BearerAuth {
+    token: ArcSwap<HeaderValue>,
+}
+impl BearerAuth {
+    /// Fails if the token has characters not allowed in a header
+    pub fn new(access_token: &str) -> Result<Self, InvalidHeaderValue> {
+        Ok(Self {
+            token: ArcSwap::from_pointee(Self::header(access_token)?),
+        })
+    }
+    /// Keeps the current token if the new one has characters not allowed in a header
+    pub fn set_access_token(&self, access_token: &str) -> Result<(), InvalidHeaderValue> {
+        self.token.store(Arc::new(Self::header(access_token)?));
+        Ok(())
+    }
+    fn header(access_token: &str) -> Result<HeaderValue, InvalidHeaderValue> {
+        let mut header = HeaderValue::from_str(&format!("Bearer {access_token}"))?;
+        header.set_sensitive(true);
+        Ok(header)
+    }
+}
+/// Without a token until one is set
+impl Default for BearerAuth {
+    fn default() -> Self {
+        Self {
+            token: ArcSwap::from_pointee(HeaderValue::from_static("Bearer ")),
+        }
+    }
+}
+impl Authenticator for BearerAuth {
+    fn authenticate(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder.header(AUTHORIZATION, self.token.load().as_ref().clone())
+    }
+    #[inline]
+    fn uses_csrf_token(&self) -> bool {
+        false
+    }
+}
+impl Authenticated for BearerAuth {}
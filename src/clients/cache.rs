@@ -0,0 +1,346 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use ahash::RandomState;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
+use itertools::{EitherOrBoth, Itertools};
+use reqwest::{IntoUrl, Method, StatusCode, Url};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sonic_rs::Value;
+use thiserror::Error;
+
+use crate::{
+    BaseClient,
+    apis::{RequestResult, RobloxError, StringError},
+    endpoints::{Endpoints, Service},
+    rate_limit::Quota,
+};
+
+use super::cassette::{RecordedResponse, reparse, request_with};
+
+/// Endpoints cached by [`CachingClient::new`], as url paths relative to the base url of their
+/// service, where `*` matches any segment
+pub const DEFAULT_TTLS: [(&str, Duration); 3] = [
+    // A place never moves to another universe
+    ("/universes/v1/places/*/universe", Duration::MAX),
+    ("/places/api-get-details", Duration::from_mins(10)),
+    ("/v1/groups/metadata", Duration::from_hours(1)),
+];
+const DEFAULT_NOT_FOUND_TTL: Duration = Duration::from_mins(1);
+const DEFAULT_CAPACITY: usize = 4096;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] sonic_rs::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    response: RecordedResponse,
+    /// Never expires when missing
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    last_used: u64,
+}
+impl Entry {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entries {
+    entries: HashMap<String, Entry, RandomState>,
+    #[serde(skip)]
+    clock: u64,
+}
+impl Entries {
+    fn get(&mut self, key: &str) -> Option<RecordedResponse> {
+        let entry = self.entries.get_mut(key)?;
+        if entry.is_expired(Utc::now()) {
+            self.entries.remove(key);
+            return None;
+        }
+        self.clock += 1;
+        entry.last_used = self.clock;
+        Some(entry.response.clone())
+    }
+    fn insert(&mut self, key: String, response: RecordedResponse, ttl: Duration, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= capacity && !self.entries.contains_key(&key) {
+            let now = Utc::now();
+            self.entries.retain(|_, entry| !entry.is_expired(now));
+        }
+        while self.entries.len() >= capacity && !self.entries.contains_key(&key) {
+            let Some(least_used) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&least_used);
+        }
+        self.clock += 1;
+        let expires_at = TimeDelta::from_std(ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl));
+        self.entries.insert(
+            key,
+            Entry {
+                response,
+                expires_at,
+                last_used: self.clock,
+            },
+        );
+    }
+}
+
+/// Caches the GET responses of the wrapped client, keyed on method, url and query
+///
+/// Only endpoints given a ttl are cached, see [`DEFAULT_TTLS`]. Not found responses are
+/// cached too, for a shorter time.
+#[derive(Debug)]
+pub struct CachingClient<C: BaseClient> {
+    inner: C,
+    ttls: Vec<(String, Duration)>,
+    default_ttl: Option<Duration>,
+    not_found_ttl: Option<Duration>,
+    capacity: usize,
+    entries: Mutex<Entries>,
+    store: Option<PathBuf>,
+}
+impl<C: BaseClient> CachingClient<C> {
+    #[must_use]
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            ttls: DEFAULT_TTLS
+                .iter()
+                .map(|(path, ttl)| (String::from(*path), *ttl))
+                .collect(),
+            default_ttl: None,
+            not_found_ttl: Some(DEFAULT_NOT_FOUND_TTL),
+            capacity: DEFAULT_CAPACITY,
+            entries: Mutex::default(),
+            store: None,
+        }
+    }
+    /// Caches the endpoints matching the path for the ttl, before any ttl set earlier
+    ///
+    /// The path is relative to the base url of the service, see [`Endpoints`], and `*` matches
+    /// any path segment, and [`Duration::MAX`] caches for as long as the client lives.
+    #[must_use]
+    pub fn with_ttl(mut self, path: &str, ttl: Duration) -> Self {
+        self.ttls.insert(0, (String::from(path), ttl));
+        self
+    }
+    /// Caches the endpoints matching no path too
+    #[must_use]
+    pub const fn with_default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+    /// `None` stops caching not found responses
+    #[must_use]
+    pub const fn with_not_found_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.not_found_ttl = ttl;
+        self
+    }
+    /// Most responses kept, evicting the least recently used ones
+    #[must_use]
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+    /// Loads the responses saved in the file, if it exists, and saves them there with [`Self::save`]
+    pub fn with_store(mut self, path: impl Into<PathBuf>) -> Result<Self, CacheError> {
+        let path = path.into();
+        match fs::read(&path) {
+            Ok(data) => *self.entries.get_mut().unwrap() = sonic_rs::from_slice(&data)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error.into()),
+        }
+        self.store = Some(path);
+        Ok(self)
+    }
+    /// Writes the unexpired responses to the store, if there is one
+    pub fn save(&self) -> Result<(), CacheError> {
+        self.store
+            .as_ref()
+            .map_or(Ok(()), |path| self.save_to(path))
+    }
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), CacheError> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Utc::now();
+        entries.entries.retain(|_, entry| !entry.is_expired(now));
+        let data = sonic_rs::to_string(&*entries)?;
+        drop(entries);
+        fs::write(path, data)?;
+        Ok(())
+    }
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().entries.clear();
+    }
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+    /// The url path after the base url of its service, or the whole path for other urls
+    fn service_path<'a>(&self, url: &'a Url) -> &'a str {
+        let endpoints = self.inner.endpoints();
+        Service::ALL
+            .iter()
+            .filter_map(|service| url.as_str().strip_prefix(endpoints.get(*service)))
+            // The longest base url, when one service's is inside another's
+            .min_by_key(|path| path.len())
+            .map_or_else(
+                || url.path(),
+                |path| path.split(['?', '#']).next().unwrap_or_default(),
+            )
+    }
+    fn ttl(&self, path: &str) -> Option<Duration> {
+        self.ttls
+            .iter()
+            .find(|(pattern, _)| path_matches(pattern, path))
+            .map(|(_, ttl)| *ttl)
+            .or(self.default_ttl)
+    }
+    fn get(&self, key: &str) -> Option<RecordedResponse> {
+        self.entries.lock().unwrap().get(key)
+    }
+    fn insert(&self, key: String, response: RecordedResponse, ttl: Duration) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, response, ttl, self.capacity);
+    }
+    async fn cached_request<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Option<Quota>,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        let url = url.into_url()?;
+        let ttl = if method == Method::GET {
+            self.ttl(self.service_path(&url))
+        } else {
+            None
+        };
+        let Some(ttl) = ttl else {
+            return request_with(&self.inner, quota, method, url, query, payload).await;
+        };
+        let query_key = query
+            .as_ref()
+            .and_then(|query| sonic_rs::to_string(query).ok())
+            .unwrap_or_default();
+        let key = format!("{method} {url} {query_key}");
+        if let Some(response) = self.get(&key) {
+            return response.decode();
+        }
+        let (response, ttl) = match request_with::<_, Value, StringError>(
+            &self.inner,
+            quota,
+            method,
+            url,
+            query,
+            payload,
+        )
+        .await
+        {
+            Ok(value) => (
+                RecordedResponse {
+                    status: StatusCode::OK.as_u16(),
+                    headers: BTreeMap::new(),
+                    body: sonic_rs::to_string(&value).unwrap(),
+                },
+                Some(ttl),
+            ),
+            Err(error) => match error.response() {
+                Some(response) => (
+                    RecordedResponse::from(response),
+                    self.not_found_ttl
+                        .filter(|_| response.status == StatusCode::NOT_FOUND),
+                ),
+                None => return Err(reparse(error)),
+            },
+        };
+        let result = response.decode();
+        if let Some(ttl) = ttl {
+            self.insert(key, response, ttl);
+        }
+        result
+    }
+}
+
+/// Whether the url path matches the pattern, `*` matching any single segment
+fn path_matches(pattern: &str, path: &str) -> bool {
+    pattern
+        .trim_matches('/')
+        .split('/')
+        .zip_longest(path.trim_matches('/').split('/'))
+        .all(|segments| {
+            matches!(segments, EitherOrBoth::Both(pattern, segment) if pattern == "*" || pattern == segment)
+        })
+}
+
+#[async_trait]
+impl<C: BaseClient + Send> BaseClient for CachingClient<C> {
+    #[inline]
+    fn endpoints(&self) -> &Endpoints {
+        self.inner.endpoints()
+    }
+    #[inline]
+    async fn acquire_quota(&self, quota: Quota) {
+        self.inner.acquire_quota(quota).await;
+    }
+    async fn request<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.cached_request(None, method, url, query, payload).await
+    }
+    /// Cached responses take no quota
+    async fn request_with_quota<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Quota,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.cached_request(Some(quota), method, url, query, payload)
+            .await
+    }
+    /// Downloads are not cached
+    #[inline]
+    async fn download(&self, url: impl IntoUrl + Send) -> RequestResult<Bytes, StringError> {
+        self.inner.download(url).await
+    }
+}
@@ -0,0 +1,366 @@
+use std::{collections::BTreeMap, fs, path::Path, sync::Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{
+    IntoUrl, Method, StatusCode,
+    header::{HeaderMap, HeaderName, HeaderValue},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sonic_rs::Value;
+use thiserror::Error;
+
+use crate::{
+    AuthenticatedClient, BaseClient,
+    apis::{Error, RequestResult, ResponseInfo, RobloxError, StringError},
+    endpoints::Endpoints,
+    rate_limit::Quota,
+};
+
+use super::decode_response;
+
+#[derive(Debug, Error)]
+pub enum CassetteError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] sonic_rs::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub query: Option<Value>,
+    pub body: Option<Value>,
+}
+impl RecordedRequest {
+    fn new(
+        method: &Method,
+        url: &str,
+        query: Option<&(impl Serialize + Send)>,
+        payload: Option<&(impl Serialize + Send)>,
+    ) -> Self {
+        Self {
+            method: String::from(method.as_str()),
+            url: String::from(url),
+            query: query.and_then(|query| sonic_rs::to_value(query).ok()),
+            body: payload.and_then(|payload| sonic_rs::to_value(payload).ok()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// Only the headers kept in [`ResponseInfo`]
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+impl RecordedResponse {
+    pub(crate) fn decode<T: DeserializeOwned, E: RobloxError>(&self) -> RequestResult<T, E> {
+        let headers = self
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect::<HeaderMap>();
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        decode_response(status, &headers, &self.body)
+    }
+}
+impl From<&ResponseInfo> for RecordedResponse {
+    fn from(value: &ResponseInfo) -> Self {
+        Self {
+            status: value.status.as_u16(),
+            headers: value
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((
+                        String::from(name.as_str()),
+                        String::from(value.to_str().ok()?),
+                    ))
+                })
+                .collect(),
+            body: value.body.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// Request and response pairs saved as json
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        Ok(sonic_rs::from_slice(&fs::read(path)?)?)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CassetteError> {
+        fs::write(path, sonic_rs::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Records the requests of the wrapped client and their responses into a [`Cassette`]
+///
+/// Requests failing without a response, like connection errors, are not recorded.
+#[derive(Debug)]
+pub struct RecordingClient<C: BaseClient> {
+    inner: C,
+    cassette: Mutex<Cassette>,
+}
+impl<C: BaseClient> RecordingClient<C> {
+    #[must_use]
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            cassette: Mutex::default(),
+        }
+    }
+    #[must_use]
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CassetteError> {
+        self.cassette.lock().unwrap().save(path)
+    }
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+    async fn record<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Option<Quota>,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        let url = url.into_url()?;
+        let request = RecordedRequest::new(&method, url.as_str(), query.as_ref(), payload.as_ref());
+        let response = match request_with::<_, Value, StringError>(
+            &self.inner,
+            quota,
+            method,
+            url,
+            query,
+            payload,
+        )
+        .await
+        {
+            Ok(value) => RecordedResponse {
+                status: StatusCode::OK.as_u16(),
+                headers: BTreeMap::new(),
+                body: sonic_rs::to_string(&value).unwrap(),
+            },
+            Err(error) => match error.response() {
+                Some(response) => RecordedResponse::from(response),
+                None => return Err(reparse(error)),
+            },
+        };
+        let result = response.decode();
+        self.cassette
+            .lock()
+            .unwrap()
+            .interactions
+            .push(Interaction { request, response });
+        result
+    }
+}
+
+#[async_trait]
+impl<C: BaseClient + Send> BaseClient for RecordingClient<C> {
+    #[inline]
+    fn endpoints(&self) -> &Endpoints {
+        self.inner.endpoints()
+    }
+    #[inline]
+    async fn acquire_quota(&self, quota: Quota) {
+        self.inner.acquire_quota(quota).await;
+    }
+    async fn request<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.record(None, method, url, query, payload).await
+    }
+    async fn request_with_quota<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Quota,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.record(Some(quota), method, url, query, payload).await
+    }
+    /// Downloads are not recorded
+    #[inline]
+    async fn download(&self, url: impl IntoUrl + Send) -> RequestResult<Bytes, StringError> {
+        self.inner.download(url).await
+    }
+}
+
+/// Sends through the client, taking the quota through it when there is one
+pub(crate) async fn request_with<C: BaseClient, T: DeserializeOwned, E: RobloxError>(
+    client: &C,
+    quota: Option<Quota>,
+    method: Method,
+    url: impl IntoUrl + Send,
+    query: Option<impl Serialize + Send>,
+    payload: Option<impl Serialize + Send>,
+) -> RequestResult<T, E> {
+    match quota {
+        Some(quota) => {
+            client
+                .request_with_quota(quota, method, url, query, payload)
+                .await
+        }
+        None => client.request(method, url, query, payload).await,
+    }
+}
+
+/// Gives errors that never reached a response the caller's error type
+pub(crate) fn reparse<E: RobloxError>(error: Error<StringError>) -> Error<E> {
+    match error {
+        Error::Request(error) => Error::Request(error),
+        Error::RateLimit(_) => Error::RateLimit(None),
+        Error::Unauthorized(_) => Error::Unauthorized(None),
+        error => E::parse(error.to_string()).into(),
+    }
+}
+
+/// Parts of a request compared when looking for its recorded response
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestMatching {
+    pub method: bool,
+    pub url: bool,
+    pub query: bool,
+    pub body: bool,
+}
+impl Default for RequestMatching {
+    fn default() -> Self {
+        Self {
+            method: true,
+            url: true,
+            query: true,
+            body: true,
+        }
+    }
+}
+impl RequestMatching {
+    fn matches(self, recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+        (!self.method || recorded.method == request.method)
+            && (!self.url || recorded.url == request.url)
+            && (!self.query || recorded.query == request.query)
+            && (!self.body || recorded.body == request.body)
+    }
+}
+
+/// Serves the responses of a [`Cassette`] without any network access
+///
+/// Matching interactions are replayed in the order they were recorded, the last one being
+/// repeated once they are all used. Requests matching none get a 404 response.
+#[derive(Debug)]
+pub struct ReplayClient {
+    interactions: Vec<Interaction>,
+    used: Mutex<Vec<bool>>,
+    matching: RequestMatching,
+    endpoints: Endpoints,
+}
+impl ReplayClient {
+    #[must_use]
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            used: Mutex::new(vec![false; cassette.interactions.len()]),
+            interactions: cassette.interactions,
+            matching: RequestMatching::default(),
+            endpoints: Endpoints::default(),
+        }
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        Cassette::load(path).map(Self::new)
+    }
+    #[must_use]
+    pub const fn with_matching(mut self, matching: RequestMatching) -> Self {
+        self.matching = matching;
+        self
+    }
+    /// Should be the endpoints the cassette was recorded with, when matching on urls
+    #[must_use]
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+    fn find(&self, request: &RecordedRequest) -> Option<&RecordedResponse> {
+        let matching = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| self.matching.matches(&interaction.request, request))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let last = *matching.last()?;
+        let mut used = self.used.lock().unwrap();
+        let index = matching
+            .into_iter()
+            .find(|index| !used[*index])
+            .unwrap_or(last);
+        used[index] = true;
+        drop(used);
+        Some(&self.interactions[index].response)
+    }
+}
+
+#[async_trait]
+impl AuthenticatedClient for ReplayClient {
+    #[inline]
+    fn authenticated_endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+    async fn authenticated_request<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        let url = url.into_url()?;
+        let request = RecordedRequest::new(&method, url.as_str(), query.as_ref(), payload.as_ref());
+        if let Some(response) = self.find(&request) {
+            return response.decode();
+        }
+        tracing::warn!(%method, %url, "no recorded interaction");
+        RecordedResponse {
+            status: StatusCode::NOT_FOUND.as_u16(),
+            headers: BTreeMap::new(),
+            body: format!("no recorded interaction for {method} {url}"),
+        }
+        .decode()
+    }
+    /// Downloads are never recorded, so they always fail
+    async fn authenticated_download(
+        &self,
+        url: impl IntoUrl + Send,
+    ) -> RequestResult<Bytes, StringError> {
+        let url = url.into_url()?;
+        Err(StringError::parse(format!("downloads are not recorded: {url}")).into())
+    }
+}
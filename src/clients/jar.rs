@@ -0,0 +1,299 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use ahash::RandomState;
+use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
+use cookie::Cookie;
+use dashmap::DashMap;
+use itertools::Itertools;
+use reqwest::{Url, cookie::CookieStore, header::HeaderValue};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
+
+use super::AUTHENTICATION_COOKIE_NAME;
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const NETSCAPE_HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+/// Domain of the cookies set without one, also matching its subdomains
+pub const DEFAULT_DOMAIN: &str = ".roblox.com";
+
+#[derive(Debug, Error)]
+pub enum JarError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] sonic_rs::Error),
+    #[error("malformed netscape cookie on line {0}")]
+    Netscape(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JarFormat {
+    /// Object mapping cookie names to [`StoredCookie`]s
+    #[default]
+    Json,
+    /// `cookies.txt` as written by curl and browser extensions
+    Netscape,
+}
+impl JarFormat {
+    /// Netscape for `.txt` files, json otherwise
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+        {
+            Self::Netscape
+        } else {
+            Self::Json
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoredCookie {
+    pub value: String,
+    /// [`DEFAULT_DOMAIN`] when absent
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    /// Never expires when absent
+    pub expires: Option<DateTime<Utc>>,
+    pub secure: bool,
+}
+impl StoredCookie {
+    /// Cookie sent to [`DEFAULT_DOMAIN`]
+    #[must_use]
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: String::from(value),
+            domain: Some(String::from(DEFAULT_DOMAIN)),
+            ..Self::default()
+        }
+    }
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= Utc::now())
+    }
+    #[must_use]
+    pub fn matches(&self, url: &Url) -> bool {
+        let domain = self
+            .domain
+            .as_deref()
+            .unwrap_or(DEFAULT_DOMAIN)
+            .trim_start_matches('.');
+        let domain_matches = url.host_str().is_some_and(|host| {
+            host.eq_ignore_ascii_case(domain)
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        });
+        let path_matches = self
+            .path
+            .as_deref()
+            .is_none_or(|path| url.path().starts_with(path));
+        domain_matches
+            && path_matches
+            && (!self.secure || url.scheme() == "https")
+            && !self.is_expired()
+    }
+    fn from_set_cookie(cookie: &Cookie<'_>, url: &Url) -> Self {
+        let expires = cookie.max_age().map_or_else(
+            || {
+                cookie
+                    .expires_datetime()
+                    .and_then(|expires| DateTime::from_timestamp(expires.unix_timestamp(), 0))
+            },
+            |max_age| Some(Utc::now() + TimeDelta::seconds(max_age.whole_seconds())),
+        );
+        Self {
+            value: String::from(cookie.value()),
+            domain: cookie.domain().or_else(|| url.host_str()).map(String::from),
+            path: cookie.path().map(String::from),
+            expires,
+            secure: cookie.secure().unwrap_or_default(),
+        }
+    }
+}
+
+/// Cookie jar shared between clones of a client, also recording cookies set by responses
+#[derive(Debug)]
+pub(crate) struct StaticSharedJar {
+    cookies: DashMap<String, StoredCookie, RandomState>,
+    auth_cookie: watch::Sender<Option<String>>,
+}
+impl Default for StaticSharedJar {
+    fn default() -> Self {
+        Self {
+            cookies: DashMap::default(),
+            auth_cookie: watch::Sender::new(None),
+        }
+    }
+}
+impl StaticSharedJar {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+    pub(crate) fn insert(&self, name: &str, value: &str) {
+        self.insert_cookie(name, StoredCookie::new(value));
+    }
+    /// Stores the domain and path the cookie is sent to even when it has none, so that
+    /// exports keep its scope
+    pub(crate) fn insert_cookie(&self, name: &str, mut cookie: StoredCookie) {
+        cookie
+            .domain
+            .get_or_insert_with(|| String::from(DEFAULT_DOMAIN));
+        cookie.path.get_or_insert_with(|| String::from("/"));
+        if is_auth_cookie(name) {
+            self.notify(Some(&cookie.value));
+        }
+        self.cookies.insert(String::from(name), cookie);
+    }
+    pub(crate) fn remove(&self, name: &str) {
+        if is_auth_cookie(name) {
+            self.notify(None);
+        }
+        self.cookies.remove(name);
+    }
+    pub(crate) fn get(&self, name: &str) -> Option<String> {
+        self.cookies
+            .get(name)
+            .filter(|cookie| !cookie.is_expired())
+            .map(|cookie| cookie.value.clone())
+    }
+    pub(crate) fn get_cookie(&self, name: &str) -> Option<StoredCookie> {
+        self.cookies.get(name).map(|cookie| cookie.clone())
+    }
+    pub(crate) fn clear(&self) {
+        self.notify(None);
+        self.cookies.clear();
+    }
+    pub(crate) fn export(&self, format: JarFormat) -> String {
+        let cookies = self
+            .cookies
+            .iter()
+            .filter(|x| !x.value().is_expired())
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect::<BTreeMap<_, _>>();
+        match format {
+            JarFormat::Json => sonic_rs::to_string_pretty(&cookies).unwrap(),
+            JarFormat::Netscape => {
+                let mut file = format!("{NETSCAPE_HEADER}\n");
+                for (name, cookie) in cookies {
+                    let domain = cookie.domain.as_deref().unwrap_or(DEFAULT_DOMAIN);
+                    writeln!(
+                        file,
+                        "{domain}\t{}\t{}\t{}\t{}\t{name}\t{}",
+                        netscape_bool(domain.starts_with('.')),
+                        cookie.path.as_deref().unwrap_or("/"),
+                        netscape_bool(cookie.secure),
+                        cookie.expires.map_or(0, |expires| expires.timestamp()),
+                        cookie.value
+                    )
+                    .unwrap();
+                }
+                file
+            }
+        }
+    }
+    pub(crate) fn import(&self, data: &str, format: JarFormat) -> Result<(), JarError> {
+        match format {
+            JarFormat::Json => {
+                let cookies = sonic_rs::from_str::<BTreeMap<String, StoredCookie>>(data)?;
+                for (name, cookie) in cookies {
+                    self.insert_cookie(&name, cookie);
+                }
+            }
+            JarFormat::Netscape => {
+                for (index, line) in data.lines().enumerate() {
+                    let line = line.trim();
+                    let line = line.strip_prefix(NETSCAPE_HTTP_ONLY_PREFIX).unwrap_or(line);
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let (name, cookie) =
+                        parse_netscape_line(line).ok_or(JarError::Netscape(index + 1))?;
+                    if !cookie.is_expired() {
+                        self.insert_cookie(name, cookie);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn subscribe(&self) -> watch::Receiver<Option<String>> {
+        self.auth_cookie.subscribe()
+    }
+    fn notify(&self, value: Option<&str>) {
+        self.auth_cookie.send_if_modified(|current| {
+            if current.as_deref() == value {
+                false
+            } else {
+                *current = value.map(String::from);
+                true
+            }
+        });
+    }
+}
+impl CookieStore for StaticSharedJar {
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let cookie_string = self
+            .cookies
+            .iter()
+            .filter(|x| x.value().matches(url))
+            .map(|x| format!("{}={}", x.key(), x.value().value))
+            .join("; ");
+
+        if cookie_string.is_empty() {
+            None
+        } else {
+            HeaderValue::from_maybe_shared(Bytes::from(cookie_string)).ok()
+        }
+    }
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        for header in cookie_headers {
+            let Some(cookie) = header
+                .to_str()
+                .ok()
+                .and_then(|header| Cookie::parse(header).ok())
+            else {
+                continue;
+            };
+            let stored = StoredCookie::from_set_cookie(&cookie, url);
+            if stored.is_expired() {
+                self.remove(cookie.name());
+            } else {
+                self.insert_cookie(cookie.name(), stored);
+            }
+        }
+    }
+}
+
+const fn is_auth_cookie(name: &str) -> bool {
+    name.eq_ignore_ascii_case(AUTHENTICATION_COOKIE_NAME)
+}
+
+const fn netscape_bool(value: bool) -> &'static str {
+    if value { "TRUE" } else { "FALSE" }
+}
+
+fn parse_netscape_line(line: &str) -> Option<(&str, StoredCookie)> {
+    let [domain, _, path, secure, expires, name, value] =
+        line.splitn(7, '\t').collect::<Vec<_>>().try_into().ok()?;
+    let expires = expires.parse::<i64>().ok()?;
+    Some((
+        name,
+        StoredCookie {
+            value: String::from(value),
+            domain: Some(String::from(domain)),
+            path: Some(String::from(path)),
+            expires: if expires == 0 {
+                None
+            } else {
+                Some(DateTime::from_timestamp(expires, 0)?)
+            },
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+        },
+    ))
+}
@@ -0,0 +1,131 @@
+use std::{fmt::Debug, sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use reqwest::{
+    Client as ReqwestClient, ClientBuilder as ReqwestClientBuilder, Request, Response,
+    header::HeaderMap,
+};
+
+use super::{
+    Client, CookieClient, HttpClient,
+    auth::{Authenticator, CookieAuth, NoAuth},
+};
+
+/// Wraps every request sent by an [`HttpClient`], including retries and csrf resubmissions
+///
+/// Middlewares run in the order they were added, each deciding whether and how to call
+/// the next one, so they can also answer without sending anything.
+#[async_trait]
+pub trait Middleware: Debug + Send + Sync {
+    async fn handle(&self, request: Request, next: Next<'_>) -> reqwest::Result<Response>;
+}
+
+/// Rest of the middleware stack, ending with the request being sent
+#[derive(Debug, Clone, Copy)]
+pub struct Next<'a> {
+    http: &'a ReqwestClient,
+    middlewares: &'a [Arc<dyn Middleware>],
+}
+impl<'a> Next<'a> {
+    pub(crate) const fn new(
+        http: &'a ReqwestClient,
+        middlewares: &'a [Arc<dyn Middleware>],
+    ) -> Self {
+        Self { http, middlewares }
+    }
+    pub async fn run(self, request: Request) -> reqwest::Result<Response> {
+        match self.middlewares.split_first() {
+            Some((middleware, middlewares)) => {
+                middleware
+                    .handle(
+                        request,
+                        Next {
+                            http: self.http,
+                            middlewares,
+                        },
+                    )
+                    .await
+            }
+            None => self.http.execute(request).await,
+        }
+    }
+}
+
+/// Composes middlewares into a [`Client`], [`CookieClient`] or any other [`HttpClient`]
+#[derive(Debug, Default)]
+pub struct MiddlewareClientBuilder {
+    builder: ReqwestClientBuilder,
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+impl MiddlewareClientBuilder {
+    #[must_use]
+    pub fn new(builder: ReqwestClientBuilder) -> Self {
+        Self {
+            builder,
+            middlewares: Vec::new(),
+        }
+    }
+    #[must_use]
+    pub fn with(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+    #[must_use]
+    pub fn with_arc(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+    #[must_use]
+    pub fn build_with_authenticator<A: Authenticator>(self, auth: A) -> HttpClient<A> {
+        let mut client = HttpClient::with_authenticator(self.builder, auth);
+        client.middlewares = Arc::new(self.middlewares);
+        client
+    }
+    #[must_use]
+    pub fn build(self) -> Client {
+        self.build_with_authenticator(NoAuth)
+    }
+    #[must_use]
+    pub fn build_with_cookie(self, auth_cookie: &str) -> CookieClient {
+        self.build_with_authenticator(CookieAuth::new(auth_cookie))
+    }
+}
+
+/// Adds headers to every request, keeping those the request already sets
+#[derive(Debug, Clone, Default)]
+pub struct SetHeaders(pub HeaderMap);
+
+#[async_trait]
+impl Middleware for SetHeaders {
+    async fn handle(&self, mut request: Request, next: Next<'_>) -> reqwest::Result<Response> {
+        for (name, value) in &self.0 {
+            if !request.headers().contains_key(name) {
+                request.headers_mut().insert(name, value.clone());
+            }
+        }
+        next.run(request).await
+    }
+}
+
+/// Logs every request with its status and duration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tracing;
+
+#[async_trait]
+impl Middleware for Tracing {
+    async fn handle(&self, request: Request, next: Next<'_>) -> reqwest::Result<Response> {
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let start = Instant::now();
+        let result = next.run(request).await;
+        let elapsed = start.elapsed();
+        match &result {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                tracing::debug!(%method, %url, status, ?elapsed, "request");
+            }
+            Err(error) => tracing::debug!(%method, %url, %error, ?elapsed, "request failed"),
+        }
+        result
+    }
+}
@@ -0,0 +1,460 @@
+use std::{fmt::Debug, sync::Arc};
+
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::{ClientBuilder as ReqwestClientBuilder, IntoUrl, Method, Url};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::{
+    AuthenticatedClient, OpenCloudClient,
+    apis::{Empty, Error, RequestResult, RobloxError, StringError},
+    endpoints::{Endpoints, Service},
+    private::RobloxErrorSealed,
+    rate_limit::Quota,
+};
+
+use super::{HttpClient, auth::BearerAuth};
+
+/// Tokens are refreshed this long before they expire
+const EXPIRY_MARGIN: TimeDelta = TimeDelta::seconds(60);
+
+#[derive(Debug, Clone, Default)]
+pub struct OAuthConfig {
+    pub client_id: String,
+    /// Absent for public clients, which must use PKCE instead
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenSet {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub id_token: Option<String>,
+    pub scope: String,
+    pub expires_at: DateTime<Utc>,
+}
+impl TokenSet {
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at - EXPIRY_MARGIN <= Utc::now()
+    }
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.split_whitespace()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    id_token: Option<String>,
+    scope: String,
+    expires_in: i64,
+}
+impl From<TokenResponse> for TokenSet {
+    fn from(value: TokenResponse) -> Self {
+        Self {
+            access_token: value.access_token,
+            refresh_token: value.refresh_token,
+            id_token: value.id_token,
+            scope: value.scope,
+            expires_at: Utc::now() + TimeDelta::seconds(value.expires_in),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenIntrospection {
+    pub active: bool,
+    pub jti: Option<String>,
+    pub iss: Option<String>,
+    pub token_type: Option<String>,
+    pub client_id: Option<String>,
+    pub aud: Option<String>,
+    pub sub: Option<String>,
+    pub scope: Option<String>,
+    pub exp: Option<i64>,
+    pub iat: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserInfo {
+    pub sub: String,
+    pub name: Option<String>,
+    pub nickname: Option<String>,
+    pub preferred_username: Option<String>,
+    pub created_at: Option<i64>,
+    pub profile: Option<String>,
+    pub picture: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct InnerOAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Error, Clone)]
+pub enum OAuthError {
+    #[error("oauth error: {error}")]
+    Valid {
+        error: String,
+        error_description: Option<String>,
+    },
+    #[error("malformed response: {0}")]
+    Malformed(String),
+}
+impl RobloxErrorSealed for OAuthError {}
+impl RobloxError for OAuthError {
+    fn parse(res: String) -> Self {
+        sonic_rs::from_str::<InnerOAuthError>(&res).map_or(Self::Malformed(res), |value| {
+            Self::Valid {
+                error: value.error,
+                error_description: value.error_description,
+            }
+        })
+    }
+    #[inline]
+    fn is_structured(&self) -> bool {
+        !matches!(self, Self::Malformed(_))
+    }
+}
+
+/// Proof key for code exchange, the verifier has to be kept until the code is exchanged
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+impl Default for Pkce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Pkce {
+    #[must_use]
+    pub fn new() -> Self {
+        let verifier = random_string();
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// Where the user has to be sent to authorize the app
+    pub url: Url,
+    /// Has to match the `state` parameter received on the redirect uri
+    pub state: String,
+    pub pkce: Pkce,
+}
+
+fn random_string() -> String {
+    let mut bytes = [0; 32];
+    getrandom::fill(&mut bytes).unwrap();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Persists the tokens of an [`OAuthClient`] whenever they change
+#[async_trait]
+pub trait TokenStore: Debug + Send + Sync {
+    async fn load(&self) -> Option<TokenSet>;
+    async fn store(&self, tokens: &TokenSet);
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore(std::sync::Mutex<Option<TokenSet>>);
+impl MemoryTokenStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self) -> Option<TokenSet> {
+        self.0.lock().unwrap().clone()
+    }
+    async fn store(&self, tokens: &TokenSet) {
+        *self.0.lock().unwrap() = Some(tokens.clone());
+    }
+}
+
+#[derive(Serialize)]
+struct TokenRequest<'a> {
+    grant_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_verifier: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<&'a str>,
+    client_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct TokenOperationRequest<'a> {
+    token: &'a str,
+    client_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<&'a str>,
+}
+
+/// Client for apps using Roblox `OAuth2`, refreshing its tokens transparently before they expire
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    client: HttpClient<BearerAuth>,
+    config: Arc<OAuthConfig>,
+    tokens: Arc<Mutex<Option<TokenSet>>>,
+    /// [`TokenSet::scope`] of the current tokens, readable without waiting for them
+    granted_scope: Arc<ArcSwapOption<String>>,
+    store: Arc<dyn TokenStore>,
+}
+impl OAuthClient {
+    #[must_use]
+    pub fn new(
+        builder: ReqwestClientBuilder,
+        config: OAuthConfig,
+        store: impl TokenStore + 'static,
+    ) -> Self {
+        Self::from_client(
+            HttpClient::with_authenticator(builder, BearerAuth::default()),
+            config,
+            store,
+        )
+    }
+    #[must_use]
+    pub fn from_client(
+        client: HttpClient<BearerAuth>,
+        config: OAuthConfig,
+        store: impl TokenStore + 'static,
+    ) -> Self {
+        Self {
+            client,
+            config: Arc::new(config),
+            tokens: Arc::new(Mutex::new(None)),
+            granted_scope: Arc::default(),
+            store: Arc::new(store),
+        }
+    }
+    fn oauth_url(&self, route: &str) -> String {
+        format!("{}oauth/{route}", self.client.endpoints.get(Service::Apis))
+    }
+    /// Builds the url users are sent to, using PKCE and a random state
+    #[must_use]
+    pub fn authorization_url(&self) -> AuthorizationRequest {
+        let state = random_string();
+        let pkce = Pkce::new();
+        let url = Url::parse_with_params(
+            &self.oauth_url("v1/authorize"),
+            [
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("scope", &self.config.scopes.join(" ")),
+                ("response_type", "code"),
+                ("state", &state),
+                ("code_challenge", &pkce.challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .unwrap();
+        AuthorizationRequest { url, state, pkce }
+    }
+    /// Exchanges the code received on the redirect uri for tokens and stores them
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        pkce_verifier: Option<&str>,
+    ) -> RequestResult<TokenSet, OAuthError> {
+        let mut tokens = self.tokens.lock().await;
+        let new_tokens = self
+            .request_tokens(TokenRequest {
+                grant_type: "authorization_code",
+                code: Some(code),
+                code_verifier: pkce_verifier,
+                refresh_token: None,
+                client_id: &self.config.client_id,
+                client_secret: self.config.client_secret.as_deref(),
+            })
+            .await?;
+        self.set_tokens(&mut tokens, new_tokens.clone()).await?;
+        Ok(new_tokens)
+    }
+    /// Current tokens, loaded from the store if needed
+    pub async fn tokens(&self) -> Option<TokenSet> {
+        let mut tokens = self.tokens.lock().await;
+        self.load_tokens(&mut tokens).await.cloned()
+    }
+    /// Refreshes the tokens even if they have not expired yet
+    pub async fn refresh(&self) -> RequestResult<TokenSet, OAuthError> {
+        let mut tokens = self.tokens.lock().await;
+        self.refresh_locked(&mut tokens).await
+    }
+    pub async fn introspect(&self, token: &str) -> RequestResult<TokenIntrospection, OAuthError> {
+        self.client
+            .request_form(
+                self.oauth_url("v1/token/introspect"),
+                &self.token_operation(token),
+            )
+            .await
+    }
+    pub async fn revoke(&self, token: &str) -> RequestResult<(), OAuthError> {
+        self.client
+            .request_form::<Option<Empty>, _>(
+                self.oauth_url("v1/token/revoke"),
+                &self.token_operation(token),
+            )
+            .await?;
+        Ok(())
+    }
+    pub async fn get_user_info(&self) -> RequestResult<UserInfo, OAuthError> {
+        self.authenticated_get(self.oauth_url("v1/userinfo"), None::<()>)
+            .await
+    }
+    fn token_operation<'a>(&'a self, token: &'a str) -> TokenOperationRequest<'a> {
+        TokenOperationRequest {
+            token,
+            client_id: &self.config.client_id,
+            client_secret: self.config.client_secret.as_deref(),
+        }
+    }
+    async fn request_tokens<E: RobloxError>(
+        &self,
+        request: TokenRequest<'_>,
+    ) -> RequestResult<TokenSet, E> {
+        let response = self
+            .client
+            .request_form::<TokenResponse, E>(self.oauth_url("v1/token"), &request)
+            .await?;
+        Ok(response.into())
+    }
+    async fn load_tokens<'a>(&self, tokens: &'a mut Option<TokenSet>) -> Option<&'a TokenSet> {
+        if tokens.is_none()
+            && let Some(stored) = self.store.load().await
+        {
+            match self
+                .client
+                .authenticator()
+                .set_access_token(&stored.access_token)
+            {
+                Ok(()) => {
+                    self.granted_scope
+                        .store(Some(Arc::new(stored.scope.clone())));
+                    *tokens = Some(stored);
+                }
+                Err(error) => tracing::warn!(%error, "ignoring stored tokens"),
+            }
+        }
+        tokens.as_ref()
+    }
+    /// Fails without storing the tokens if the access token cannot be sent in a header
+    async fn set_tokens<E: RobloxError>(
+        &self,
+        tokens: &mut Option<TokenSet>,
+        new_tokens: TokenSet,
+    ) -> RequestResult<(), E> {
+        self.client
+            .authenticator()
+            .set_access_token(&new_tokens.access_token)
+            .map_err(|error| E::parse(format!("invalid access token: {error}")))?;
+        self.granted_scope
+            .store(Some(Arc::new(new_tokens.scope.clone())));
+        self.store.store(&new_tokens).await;
+        *tokens = Some(new_tokens);
+        Ok(())
+    }
+    async fn refresh_locked<E: RobloxError>(
+        &self,
+        tokens: &mut Option<TokenSet>,
+    ) -> RequestResult<TokenSet, E> {
+        let refresh_token = self
+            .load_tokens(tokens)
+            .await
+            .ok_or(Error::Unauthorized(None))?
+            .refresh_token
+            .clone();
+        let new_tokens = self
+            .request_tokens(TokenRequest {
+                grant_type: "refresh_token",
+                code: None,
+                code_verifier: None,
+                refresh_token: Some(&refresh_token),
+                client_id: &self.config.client_id,
+                client_secret: self.config.client_secret.as_deref(),
+            })
+            .await?;
+        tracing::debug!(expires_at = %new_tokens.expires_at, "refreshed oauth tokens");
+        self.set_tokens(tokens, new_tokens.clone()).await?;
+        Ok(new_tokens)
+    }
+    async fn ensure_fresh<E: RobloxError>(&self) -> RequestResult<(), E> {
+        let mut tokens = self.tokens.lock().await;
+        let expired = self
+            .load_tokens(&mut tokens)
+            .await
+            .ok_or(Error::Unauthorized(None))?
+            .is_expired();
+        if expired {
+            self.refresh_locked::<E>(&mut tokens).await?;
+        }
+        drop(tokens);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuthenticatedClient for OAuthClient {
+    #[inline]
+    fn authenticated_endpoints(&self) -> &Endpoints {
+        &self.client.endpoints
+    }
+    #[inline]
+    async fn authenticated_acquire_quota(&self, quota: Quota) {
+        self.client.acquire_quota(quota).await;
+    }
+    async fn authenticated_request<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.ensure_fresh::<E>().await?;
+        self.client.request(method, url, query, payload).await
+    }
+    #[inline]
+    async fn authenticated_download(
+        &self,
+        url: impl IntoUrl + Send,
+    ) -> RequestResult<Bytes, StringError> {
+        self.client.download(url).await
+    }
+}
+
+impl OpenCloudClient for OAuthClient {
+    /// Checks the scopes granted with the tokens, or the requested ones until tokens are loaded
+    fn has_scope(&self, scope: &str) -> bool {
+        self.granted_scope.load().as_deref().map_or_else(
+            || {
+                self.config
+                    .scopes
+                    .iter()
+                    .any(|requested| requested == scope)
+            },
+            |granted| granted.split_whitespace().any(|granted| granted == scope),
+        )
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{ClientBuilder as ReqwestClientBuilder, IntoUrl, Method, Proxy};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    AuthenticatedClient, BaseClient, OpenCloudClient,
+    apis::{Error, RequestResult, RobloxError, StringError},
+    endpoints::Endpoints,
+    rate_limit::Quota,
+};
+
+use super::{
+    Client, HttpClient,
+    auth::{ApiKeyAuth, Authenticated, Authenticator, NoAuth},
+};
+
+#[derive(Debug)]
+struct PooledProxy<A: Authenticator> {
+    client: HttpClient<A>,
+    evicted_until: Mutex<Option<Instant>>,
+}
+impl<A: Authenticator> PooledProxy<A> {
+    fn evicted_until(&self, now: Instant) -> Option<Instant> {
+        let mut evicted_until = self.evicted_until.lock().unwrap();
+        if evicted_until.is_some_and(|until| until <= now) {
+            *evicted_until = None;
+        }
+        *evicted_until
+    }
+}
+
+/// Spreads requests across proxies, each with its own client and csrf token
+///
+/// Proxies returning connection errors or rate limits are evicted for
+/// [`ProxyPool::with_eviction`], 30 seconds by default. Quotas are taken through the proxy
+/// each request is sent through, so each proxy is held to its own rate limit.
+///
+/// Pools of clients with credentials, built with [`ProxyPool::from_clients`], can call the apis
+/// those credentials give access to.
+#[derive(Debug)]
+pub struct ProxyPool<A: Authenticator = NoAuth> {
+    proxies: Vec<PooledProxy<A>>,
+    next: AtomicUsize,
+    eviction: Duration,
+}
+impl ProxyPool {
+    /// Builds one client per proxy, with `builder` called for each of them
+    #[must_use]
+    pub fn new(
+        proxies: impl IntoIterator<Item = Proxy>,
+        mut builder: impl FnMut() -> ReqwestClientBuilder,
+    ) -> Self {
+        Self::from_clients(
+            proxies
+                .into_iter()
+                .map(|proxy| Client::new(builder().proxy(proxy))),
+        )
+    }
+}
+impl<A: Authenticator> ProxyPool<A> {
+    #[must_use]
+    pub fn from_clients(clients: impl IntoIterator<Item = HttpClient<A>>) -> Self {
+        let proxies = clients
+            .into_iter()
+            .map(|client| PooledProxy {
+                client,
+                evicted_until: Mutex::new(None),
+            })
+            .collect::<Vec<_>>();
+        assert!(!proxies.is_empty(), "proxy pool needs at least one proxy");
+        Self {
+            proxies,
+            next: AtomicUsize::new(0),
+            eviction: Duration::from_secs(30),
+        }
+    }
+    #[must_use]
+    pub const fn with_eviction(mut self, eviction: Duration) -> Self {
+        self.eviction = eviction;
+        self
+    }
+    pub fn clients(&self) -> impl Iterator<Item = &HttpClient<A>> {
+        self.proxies.iter().map(|proxy| &proxy.client)
+    }
+    /// Number of proxies currently in rotation
+    #[must_use]
+    pub fn available(&self) -> usize {
+        let now = Instant::now();
+        self.proxies
+            .iter()
+            .filter(|proxy| proxy.evicted_until(now).is_none())
+            .count()
+    }
+    /// Client always using the same proxy for the same key while that proxy is not evicted
+    #[must_use]
+    pub fn sticky(&self, key: impl Hash) -> StickyProxy<'_, A> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        #[allow(clippy::cast_possible_truncation)]
+        let start = hasher.finish() as usize % self.proxies.len();
+        StickyProxy { pool: self, start }
+    }
+    /// First proxy in rotation starting from `start`, or the one evicted the shortest if none are
+    fn select(&self, start: usize) -> &PooledProxy<A> {
+        let now = Instant::now();
+        let len = self.proxies.len();
+        let mut soonest: Option<(usize, Instant)> = None;
+        for offset in 0..len {
+            let index = (start + offset) % len;
+            match self.proxies[index].evicted_until(now) {
+                None => return &self.proxies[index],
+                Some(until) => {
+                    if soonest.is_none_or(|(_, soonest)| until < soonest) {
+                        soonest = Some((index, until));
+                    }
+                }
+            }
+        }
+        &self.proxies[soonest.map_or(start % len, |(index, _)| index)]
+    }
+    fn next_proxy(&self) -> &PooledProxy<A> {
+        self.select(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+    #[inline]
+    const fn pool(&self) -> &Self {
+        self
+    }
+    #[inline]
+    fn proxy(&self) -> &PooledProxy<A> {
+        self.next_proxy()
+    }
+    /// Sends the request through the proxy, taking the quota through that same proxy
+    async fn request_through<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        proxy: &PooledProxy<A>,
+        quota: Option<Quota>,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        if let Some(quota) = quota {
+            proxy.client.acquire_quota(quota).await;
+        }
+        let result = proxy.client.request(method, url, query, payload).await;
+        self.record(proxy, &result);
+        result
+    }
+    async fn download_through(
+        &self,
+        proxy: &PooledProxy<A>,
+        url: impl IntoUrl + Send,
+    ) -> RequestResult<Bytes, StringError> {
+        let result = proxy.client.download(url).await;
+        self.record(proxy, &result);
+        result
+    }
+    /// Evicts the proxy if it was rate limited or could not connect
+    fn record<T, E: RobloxError>(&self, proxy: &PooledProxy<A>, result: &RequestResult<T, E>) {
+        let evict = match result {
+            Err(Error::RateLimit(_)) => true,
+            Err(Error::Request(error)) => error.is_connect() || error.is_timeout(),
+            _ => false,
+        };
+        if evict {
+            tracing::warn!(eviction = ?self.eviction, "evicting proxy");
+            *proxy.evicted_until.lock().unwrap() = Some(Instant::now() + self.eviction);
+        }
+    }
+}
+
+/// View of a [`ProxyPool`] pinned to the proxy chosen for a key
+#[derive(Debug)]
+pub struct StickyProxy<'a, A: Authenticator = NoAuth> {
+    pool: &'a ProxyPool<A>,
+    start: usize,
+}
+impl<A: Authenticator> Clone for StickyProxy<'_, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<A: Authenticator> Copy for StickyProxy<'_, A> {}
+impl<A: Authenticator> StickyProxy<'_, A> {
+    #[inline]
+    const fn pool(&self) -> &ProxyPool<A> {
+        self.pool
+    }
+    #[inline]
+    fn proxy(&self) -> &PooledProxy<A> {
+        self.pool.select(self.start)
+    }
+}
+
+/// Implements [`BaseClient`] for a pool view of clients which are not [`Authenticated`]
+macro_rules! impl_base_client {
+    ($client: ty) => {
+        #[async_trait]
+        impl BaseClient for $client {
+            #[inline]
+            fn endpoints(&self) -> &Endpoints {
+                &self.pool().proxies[0].client.endpoints
+            }
+            async fn request<T: DeserializeOwned, E: RobloxError>(
+                &self,
+                method: Method,
+                url: impl IntoUrl + Send,
+                query: Option<impl Serialize + Send>,
+                payload: Option<impl Serialize + Send>,
+            ) -> RequestResult<T, E> {
+                self.pool()
+                    .request_through(self.proxy(), None, method, url, query, payload)
+                    .await
+            }
+            /// Takes the quota through the proxy the request is sent through
+            async fn request_with_quota<T: DeserializeOwned, E: RobloxError>(
+                &self,
+                quota: Quota,
+                method: Method,
+                url: impl IntoUrl + Send,
+                query: Option<impl Serialize + Send>,
+                payload: Option<impl Serialize + Send>,
+            ) -> RequestResult<T, E> {
+                self.pool()
+                    .request_through(self.proxy(), Some(quota), method, url, query, payload)
+                    .await
+            }
+            async fn download(
+                &self,
+                url: impl IntoUrl + Send,
+            ) -> RequestResult<Bytes, StringError> {
+                self.pool().download_through(self.proxy(), url).await
+            }
+        }
+    };
+}
+
+/// Implements [`AuthenticatedClient`] for a pool view of [`Authenticated`] clients
+macro_rules! impl_authenticated_client {
+    ($client: ty) => {
+        #[async_trait]
+        impl<A: Authenticated> AuthenticatedClient for $client {
+            #[inline]
+            fn authenticated_endpoints(&self) -> &Endpoints {
+                &self.pool().proxies[0].client.endpoints
+            }
+            async fn authenticated_request<T: DeserializeOwned, E: RobloxError>(
+                &self,
+                method: Method,
+                url: impl IntoUrl + Send,
+                query: Option<impl Serialize + Send>,
+                payload: Option<impl Serialize + Send>,
+            ) -> RequestResult<T, E> {
+                self.pool()
+                    .request_through(self.proxy(), None, method, url, query, payload)
+                    .await
+            }
+            /// Takes the quota through the proxy the request is sent through
+            async fn authenticated_request_with_quota<T: DeserializeOwned, E: RobloxError>(
+                &self,
+                quota: Quota,
+                method: Method,
+                url: impl IntoUrl + Send,
+                query: Option<impl Serialize + Send>,
+                payload: Option<impl Serialize + Send>,
+            ) -> RequestResult<T, E> {
+                self.pool()
+                    .request_through(self.proxy(), Some(quota), method, url, query, payload)
+                    .await
+            }
+            async fn authenticated_download(
+                &self,
+                url: impl IntoUrl + Send,
+            ) -> RequestResult<Bytes, StringError> {
+                self.pool().download_through(self.proxy(), url).await
+            }
+        }
+    };
+}
+
+impl_base_client!(ProxyPool<NoAuth>);
+impl_base_client!(StickyProxy<'_, NoAuth>);
+impl_base_client!(ProxyPool<ApiKeyAuth>);
+impl_base_client!(StickyProxy<'_, ApiKeyAuth>);
+impl_authenticated_client!(ProxyPool<A>);
+impl_authenticated_client!(StickyProxy<'_, A>);
+
+impl OpenCloudClient for ProxyPool<ApiKeyAuth> {
+    #[inline]
+    fn has_scope(&self, scope: &str) -> bool {
+        self.proxies[0].client.has_scope(scope)
+    }
+}
+impl OpenCloudClient for StickyProxy<'_, ApiKeyAuth> {
+    #[inline]
+    fn has_scope(&self, scope: &str) -> bool {
+        self.pool().proxies[0].client.has_scope(scope)
+    }
+}
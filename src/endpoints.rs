@@ -0,0 +1,89 @@
+use std::sync::LazyLock;
+
+pub(crate) static DEFAULT_ENDPOINTS: LazyLock<Endpoints> = LazyLock::new(Endpoints::new);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Service {
+    Apis,
+    Auth,
+    Economy,
+    Games,
+    Groups,
+    Thumbnails,
+    TwoStepVerification,
+    Users,
+    Www,
+}
+impl Service {
+    pub const ALL: [Self; 9] = [
+        Self::Apis,
+        Self::Auth,
+        Self::Economy,
+        Self::Games,
+        Self::Groups,
+        Self::Thumbnails,
+        Self::TwoStepVerification,
+        Self::Users,
+        Self::Www,
+    ];
+    #[must_use]
+    pub const fn subdomain(self) -> &'static str {
+        match self {
+            Self::Apis => "apis",
+            Self::Auth => "auth",
+            Self::Economy => "economy",
+            Self::Games => "games",
+            Self::Groups => "groups",
+            Self::Thumbnails => "thumbnails",
+            Self::TwoStepVerification => "twostepverification",
+            Self::Users => "users",
+            Self::Www => "www",
+        }
+    }
+}
+
+/// Base urls used for every service, always ending with a `/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints([String; Service::ALL.len()]);
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Endpoints {
+    /// Endpoints pointing at `roblox.com`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_domain("roblox.com")
+    }
+    /// Endpoints pointing at `https://<service>.<domain>/`, e.g. `roproxy.com`
+    #[must_use]
+    pub fn with_domain(domain: &str) -> Self {
+        Self::from_fn(|service| format!("https://{}.{domain}/", service.subdomain()))
+    }
+    #[must_use]
+    pub fn from_fn(mut base_url: impl FnMut(Service) -> String) -> Self {
+        Self(Service::ALL.map(|service| normalize(base_url(service))))
+    }
+    #[must_use]
+    pub fn with(mut self, service: Service, base_url: impl Into<String>) -> Self {
+        self.set(service, base_url);
+        self
+    }
+    pub fn set(&mut self, service: Service, base_url: impl Into<String>) {
+        self.0[service as usize] = normalize(base_url.into());
+    }
+    #[inline]
+    #[must_use]
+    pub fn get(&self, service: Service) -> &str {
+        &self.0[service as usize]
+    }
+}
+
+fn normalize(mut base_url: String) -> String {
+    if !base_url.ends_with('/') {
+        base_url.push('/');
+    }
+    base_url
+}
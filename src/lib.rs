@@ -5,18 +5,38 @@
     clippy::module_name_repetitions
 )]
 
-use apis::{RequestResult, RobloxError};
+use apis::{RequestResult, RobloxError, StringError};
 use async_trait::async_trait;
+use bytes::Bytes;
+use clients::DEFAULT_CLIENT;
+use endpoints::{DEFAULT_ENDPOINTS, Endpoints};
+use rate_limit::Quota;
 use reqwest::{IntoUrl, Method};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{Serialize, de::DeserializeOwned};
 
 pub mod apis;
+pub mod batcher;
+pub mod challenge;
 pub mod clients;
+pub mod endpoints;
 pub(crate) mod private;
+pub mod rate_limit;
+pub mod retry;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub(crate) mod utils;
 
 #[async_trait]
 pub trait BaseClient: Sync {
+    #[inline]
+    fn endpoints(&self) -> &Endpoints {
+        &DEFAULT_ENDPOINTS
+    }
+    /// Waits for a free slot of the endpoint's quota, if the client enforces rate limits
+    ///
+    /// Pools only take quotas in [`Self::request_with_quota`], through the client sending it.
+    #[inline]
+    async fn acquire_quota(&self, _quota: Quota) {}
     async fn request<T: DeserializeOwned, E: RobloxError>(
         &self,
         method: Method,
@@ -24,6 +44,30 @@ pub trait BaseClient: Sync {
         query: Option<impl Serialize + Send>,
         payload: Option<impl Serialize + Send>,
     ) -> RequestResult<T, E>;
+    /// Sends the request once a slot of the endpoint's quota is free, if the client enforces
+    /// rate limits
+    ///
+    /// Clients picking where each request goes, like the pools, take the quota through the
+    /// one the request is sent with.
+    #[inline]
+    async fn request_with_quota<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Quota,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.acquire_quota(quota).await;
+        self.request(method, url, query, payload).await
+    }
+    /// Fetches a raw body, like an image from the cdn, without authenticating the request
+    ///
+    /// Clients wrapping others should forward it, the default using a plain client.
+    #[inline]
+    async fn download(&self, url: impl IntoUrl + Send) -> RequestResult<Bytes, StringError> {
+        DEFAULT_CLIENT.download(url).await
+    }
     #[inline]
     async fn get<T: DeserializeOwned, E: RobloxError>(
         &self,
@@ -67,6 +111,14 @@ pub trait BaseClient: Sync {
 }
 #[async_trait]
 impl<C: AuthenticatedClient> BaseClient for C {
+    #[inline]
+    fn endpoints(&self) -> &Endpoints {
+        self.authenticated_endpoints()
+    }
+    #[inline]
+    async fn acquire_quota(&self, quota: Quota) {
+        self.authenticated_acquire_quota(quota).await;
+    }
     #[inline]
     async fn request<T: DeserializeOwned, E: RobloxError>(
         &self,
@@ -78,10 +130,32 @@ impl<C: AuthenticatedClient> BaseClient for C {
         self.authenticated_request(method, url, query, payload)
             .await
     }
+    #[inline]
+    async fn request_with_quota<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Quota,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.authenticated_request_with_quota(quota, method, url, query, payload)
+            .await
+    }
+    #[inline]
+    async fn download(&self, url: impl IntoUrl + Send) -> RequestResult<Bytes, StringError> {
+        self.authenticated_download(url).await
+    }
 }
 
 #[async_trait]
 pub trait AuthenticatedClient: Sync {
+    #[inline]
+    fn authenticated_endpoints(&self) -> &Endpoints {
+        &DEFAULT_ENDPOINTS
+    }
+    #[inline]
+    async fn authenticated_acquire_quota(&self, _quota: Quota) {}
     async fn authenticated_request<T: DeserializeOwned, E: RobloxError>(
         &self,
         method: Method,
@@ -89,6 +163,28 @@ pub trait AuthenticatedClient: Sync {
         query: Option<impl Serialize + Send>,
         payload: Option<impl Serialize + Send>,
     ) -> RequestResult<T, E>;
+    /// See [`BaseClient::request_with_quota`]
+    #[inline]
+    async fn authenticated_request_with_quota<T: DeserializeOwned, E: RobloxError>(
+        &self,
+        quota: Quota,
+        method: Method,
+        url: impl IntoUrl + Send,
+        query: Option<impl Serialize + Send>,
+        payload: Option<impl Serialize + Send>,
+    ) -> RequestResult<T, E> {
+        self.authenticated_acquire_quota(quota).await;
+        self.authenticated_request(method, url, query, payload)
+            .await
+    }
+    /// See [`BaseClient::download`]
+    #[inline]
+    async fn authenticated_download(
+        &self,
+        url: impl IntoUrl + Send,
+    ) -> RequestResult<Bytes, StringError> {
+        DEFAULT_CLIENT.download(url).await
+    }
     #[inline]
     async fn authenticated_get<T: DeserializeOwned, E: RobloxError>(
         &self,
@@ -135,3 +231,12 @@ pub trait AuthenticatedClient: Sync {
             .await
     }
 }
+
+/// Client able to call the Open Cloud apis, see [`apis::open_cloud::OpenCloudApi`]
+pub trait OpenCloudClient: BaseClient {
+    /// Whether the credentials may grant the scope, checked before sending a request
+    #[inline]
+    fn has_scope(&self, _scope: &str) -> bool {
+        true
+    }
+}
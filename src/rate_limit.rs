@@ -0,0 +1,83 @@
+use std::{sync::Mutex, time::Duration};
+
+use ahash::RandomState;
+use dashmap::DashMap;
+use tokio::time::Instant;
+
+/// Documented request limit of an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quota {
+    pub endpoint: &'static str,
+    pub requests: u32,
+    pub period: Duration,
+}
+impl Quota {
+    #[must_use]
+    pub const fn new(endpoint: &'static str, requests: u32, period: Duration) -> Self {
+        Self {
+            endpoint,
+            requests,
+            period,
+        }
+    }
+    fn refill_rate(&self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+impl Bucket {
+    fn full(quota: &Quota) -> Self {
+        Self {
+            tokens: f64::from(quota.requests),
+            updated: Instant::now(),
+        }
+    }
+    fn take(&mut self, quota: &Quota) -> Result<(), Duration> {
+        let now = Instant::now();
+        let rate = quota.refill_rate();
+        self.tokens = rate
+            .mul_add(now.duration_since(self.updated).as_secs_f64(), self.tokens)
+            .min(f64::from(quota.requests));
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// Token bucket limiter keyed by endpoint and an optional partition, e.g. an account or a proxy
+///
+/// Share one limiter between clients with [`std::sync::Arc`] so that they wait for the same slots.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: DashMap<(&'static str, Option<String>), Mutex<Bucket>, RandomState>,
+}
+impl RateLimiter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Takes a slot if one is free, otherwise returns how long until the next one frees up
+    pub fn try_acquire(&self, quota: &Quota, partition: Option<&str>) -> Result<(), Duration> {
+        let bucket = self
+            .buckets
+            .entry((quota.endpoint, partition.map(String::from)))
+            .or_insert_with(|| Mutex::new(Bucket::full(quota)));
+        bucket.lock().unwrap().take(quota)
+    }
+    /// Waits until a slot is free and takes it
+    pub async fn acquire(&self, quota: &Quota, partition: Option<&str>) {
+        while let Err(wait) = self.try_acquire(quota, partition) {
+            tracing::debug!(endpoint = quota.endpoint, ?wait, "waiting for rate limit");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
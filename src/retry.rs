@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Method, Response, StatusCode,
+    header::{HeaderMap, RETRY_AFTER},
+};
+
+/// Opt-in policy for resending requests that failed because of rate limits,
+/// server errors or connection errors
+///
+/// Requests other than GET, HEAD, PUT and DELETE may already have been applied when they
+/// timed out or failed on the server, so they are only resent when rate limited or when the
+/// connection could not be made, unless [`Self::retry_non_idempotent`] is set.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+    pub respect_retry_after: bool,
+    pub retry_server_errors: bool,
+    pub retry_connection_errors: bool,
+    /// Also resends POST and PATCH requests on server errors and timeouts
+    ///
+    /// Meant for a single request known to be safe to repeat, through a client cloned with
+    /// this policy.
+    pub retry_non_idempotent: bool,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            respect_retry_after: true,
+            retry_server_errors: true,
+            retry_connection_errors: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+impl RetryPolicy {
+    #[must_use]
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+    #[must_use]
+    pub const fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+    #[must_use]
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+    #[must_use]
+    pub const fn with_retry_after(mut self, respect_retry_after: bool) -> Self {
+        self.respect_retry_after = respect_retry_after;
+        self
+    }
+    #[must_use]
+    pub const fn with_server_errors(mut self, retry_server_errors: bool) -> Self {
+        self.retry_server_errors = retry_server_errors;
+        self
+    }
+    #[must_use]
+    pub const fn with_connection_errors(mut self, retry_connection_errors: bool) -> Self {
+        self.retry_connection_errors = retry_connection_errors;
+        self
+    }
+    #[must_use]
+    pub const fn with_non_idempotent(mut self, retry_non_idempotent: bool) -> Self {
+        self.retry_non_idempotent = retry_non_idempotent;
+        self
+    }
+    /// Exponential backoff for the given attempt, starting at 1
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        if self.jitter {
+            let half = delay / 2;
+            half + half.mul_f64(fastrand::f64())
+        } else {
+            delay
+        }
+    }
+    fn retry_delay(&self, attempt: u32, headers: &HeaderMap) -> Duration {
+        if self.respect_retry_after
+            && let Some(retry_after) = parse_retry_after(headers)
+        {
+            return retry_after.min(self.max_delay);
+        }
+        self.backoff(attempt)
+    }
+    fn should_retry(
+        &self,
+        attempt: u32,
+        method: &Method,
+        result: &reqwest::Result<Response>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let may_repeat = self.retry_non_idempotent || is_idempotent(method);
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                if status == StatusCode::TOO_MANY_REQUESTS
+                    || (self.retry_server_errors && may_repeat && status.is_server_error())
+                {
+                    Some(self.retry_delay(attempt, response.headers()))
+                } else {
+                    None
+                }
+            }
+            Err(error) => (self.retry_connection_errors
+                && (error.is_connect() || (may_repeat && error.is_timeout())))
+            .then(|| self.backoff(attempt)),
+        }
+    }
+}
+
+/// Parses a `Retry-After` header given either in seconds or as an http date
+#[must_use]
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Whether sending the request twice has the same effect as sending it once
+const fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+    )
+}
+
+pub(crate) async fn send_with_retry<F, Fut>(
+    policy: Option<&RetryPolicy>,
+    method: &Method,
+    mut send: F,
+) -> reqwest::Result<Response>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = reqwest::Result<Response>>,
+{
+    let Some(policy) = policy else {
+        return send().await;
+    };
+    let mut attempt = 1;
+    loop {
+        let result = send().await;
+        let Some(delay) = policy.should_retry(attempt, method, &result) else {
+            return result;
+        };
+        match &result {
+            Ok(response) => tracing::warn!(
+                attempt,
+                status = response.status().as_u16(),
+                url = %response.url(),
+                ?delay,
+                "retrying request"
+            ),
+            Err(error) => tracing::warn!(attempt, %error, ?delay, "retrying request"),
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
@@ -0,0 +1,729 @@
+//! Local fake of the Roblox endpoints called by this crate, driven by a seedable [`MockState`]
+//!
+//! Every service is served under its own path prefix, see [`MockRoblox::endpoints`].
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, Request, State},
+    http::{
+        HeaderMap, Method, StatusCode,
+        header::{COOKIE, RETRY_AFTER},
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use reqwest::ClientBuilder;
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, task::JoinHandle};
+use uuid::Uuid;
+
+use crate::{
+    clients::{
+        AUTHENTICATION_COOKIE_NAME, Client, CookieClient, auth::CookieAuth, jar::StoredCookie,
+    },
+    endpoints::Endpoints,
+};
+
+const CSRF_TOKEN_HEADER: &str = "x-csrf-token";
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerPlayer {
+    pub player_token: String,
+    pub id: u64,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MockGameServer {
+    pub id: Uuid,
+    pub max_players: u16,
+    pub playing: u16,
+    pub player_tokens: Vec<String>,
+    pub players: Vec<MockServerPlayer>,
+    pub fps: f32,
+    pub ping: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockUser {
+    pub id: u64,
+    pub name: String,
+    pub display_name: String,
+    pub has_verified_badge: bool,
+}
+impl MockUser {
+    #[must_use]
+    pub fn new(id: u64, name: &str) -> Self {
+        Self {
+            id,
+            name: String::from(name),
+            display_name: String::from(name),
+            has_verified_badge: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MockRole {
+    pub id: u64,
+    pub name: String,
+    pub rank: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockGroupMember {
+    pub user_id: u64,
+    pub role: MockRole,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockGroup {
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    pub owner: Option<u64>,
+    pub created: DateTime<Utc>,
+    pub has_verified_badge: bool,
+    pub public_entry_allowed: bool,
+    pub members: Vec<MockGroupMember>,
+}
+impl MockGroup {
+    #[must_use]
+    pub fn new(id: u64, name: &str) -> Self {
+        Self {
+            id,
+            name: String::from(name),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockThumbnail {
+    Completed(String),
+    Pending,
+    Blocked,
+}
+
+/// Response sent instead of the real one, e.g. to exercise retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockFailure {
+    pub status: StatusCode,
+    /// Seconds sent in the `Retry-After` header
+    pub retry_after: Option<u64>,
+}
+
+/// Everything the mock serves, which can be changed while it is running
+#[derive(Debug, Clone)]
+pub struct MockState {
+    /// Public servers of each place
+    pub servers: HashMap<u64, Vec<MockGameServer>>,
+    pub groups: HashMap<u64, MockGroup>,
+    pub users: HashMap<u64, MockUser>,
+    /// Thumbnails of each target, whatever their type
+    pub thumbnails: HashMap<u64, MockThumbnail>,
+    /// User behind each player token, whose thumbnail is served for the token
+    pub player_tokens: HashMap<String, u64>,
+    pub group_funds: HashMap<u64, u64>,
+    pub csrf_token: String,
+    /// Whether requests other than GET need the csrf token
+    pub require_csrf: bool,
+    /// Only accepts this `.ROBLOSECURITY` cookie when set, any otherwise
+    pub auth_cookie: Option<String>,
+    /// Answered in order before anything else
+    pub failures: VecDeque<MockFailure>,
+    /// `METHOD /path` of every request received
+    pub requests: Vec<String>,
+}
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            servers: HashMap::new(),
+            groups: HashMap::new(),
+            users: HashMap::new(),
+            thumbnails: HashMap::new(),
+            player_tokens: HashMap::new(),
+            group_funds: HashMap::new(),
+            csrf_token: String::from("mock-csrf-token"),
+            require_csrf: true,
+            auth_cookie: None,
+            failures: VecDeque::new(),
+            requests: Vec::new(),
+        }
+    }
+}
+impl MockState {
+    #[must_use]
+    pub fn with_servers(mut self, place: u64, servers: Vec<MockGameServer>) -> Self {
+        self.servers.insert(place, servers);
+        self
+    }
+    #[must_use]
+    pub fn with_group(mut self, group: MockGroup) -> Self {
+        self.groups.insert(group.id, group);
+        self
+    }
+    #[must_use]
+    pub fn with_user(mut self, user: MockUser) -> Self {
+        self.users.insert(user.id, user);
+        self
+    }
+    #[must_use]
+    pub fn with_thumbnail(mut self, target: u64, thumbnail: MockThumbnail) -> Self {
+        self.thumbnails.insert(target, thumbnail);
+        self
+    }
+    #[must_use]
+    pub fn with_player_token(mut self, token: &str, user: u64) -> Self {
+        self.player_tokens.insert(String::from(token), user);
+        self
+    }
+    #[must_use]
+    pub fn with_group_funds(mut self, group: u64, robux: u64) -> Self {
+        self.group_funds.insert(group, robux);
+        self
+    }
+    pub fn fail_next(&mut self, failure: MockFailure) {
+        self.failures.push_back(failure);
+    }
+    fn user_name(&self, id: u64) -> (String, String) {
+        self.users.get(&id).map_or_else(
+            || (format!("User{id}"), format!("User{id}")),
+            |user| (user.name.clone(), user.display_name.clone()),
+        )
+    }
+    fn detailed_user(&self, id: u64) -> DetailedUser {
+        let (username, display_name) = self.user_name(id);
+        DetailedUser {
+            has_verified_badge: self
+                .users
+                .get(&id)
+                .is_some_and(|user| user.has_verified_badge),
+            user_id: id,
+            username,
+            display_name,
+        }
+    }
+}
+
+type SharedState = Arc<Mutex<MockState>>;
+
+/// Mock server running on a local port until dropped
+#[derive(Debug)]
+pub struct MockRoblox {
+    address: SocketAddr,
+    state: SharedState,
+    task: JoinHandle<()>,
+}
+impl MockRoblox {
+    pub async fn start(state: MockState) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(state));
+        let router = router(state.clone());
+        let task = tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, router).await {
+                tracing::error!(%error, "mock server stopped");
+            }
+        });
+        Ok(Self {
+            address,
+            state,
+            task,
+        })
+    }
+    #[must_use]
+    pub const fn address(&self) -> SocketAddr {
+        self.address
+    }
+    /// Endpoints pointing every service at its prefix on this server
+    #[must_use]
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::from_fn(|service| format!("http://{}/{}/", self.address, service.subdomain()))
+    }
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+    #[must_use]
+    pub fn client(&self) -> Client {
+        Client::new(ClientBuilder::new()).with_endpoints(self.endpoints())
+    }
+    /// Client with the cookie scoped to this server, as cookies go to `.roblox.com` by default
+    #[must_use]
+    pub fn cookie_client(&self, auth_cookie: &str) -> CookieClient {
+        let client = CookieClient::with_authenticator(ClientBuilder::new(), CookieAuth::default())
+            .with_endpoints(self.endpoints());
+        client.authenticator().jar.insert_cookie(
+            AUTHENTICATION_COOKIE_NAME,
+            StoredCookie {
+                domain: Some(self.address.ip().to_string()),
+                ..StoredCookie::new(auth_cookie)
+            },
+        );
+        client
+    }
+}
+impl Drop for MockRoblox {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn router(state: SharedState) -> Router {
+    let games = Router::new().route("/v1/games/{place}/servers/{type}", get(public_servers));
+    let groups = Router::new()
+        .route("/v1/groups/{group}", get(group_info))
+        .route("/v1/groups/{group}/users", get(group_members))
+        .route("/v2/groups", get(group_info_batch));
+    let users = Router::new()
+        .route("/v1/users", post(users_by_id))
+        .route("/v1/usernames/users", post(users_by_username));
+    let thumbnails = Router::new().route("/v1/batch", post(batch_thumbnails));
+    let economy = Router::new().route("/v1/groups/{group}/currency", get(group_funds));
+    let auth = Router::new().route("/v2/logout", post(logout));
+    Router::new()
+        .nest("/games", games)
+        .nest("/groups", groups)
+        .nest("/users", users)
+        .nest("/thumbnails", thumbnails)
+        .nest("/economy", economy)
+        .nest("/auth", auth)
+        .layer(middleware::from_fn_with_state(state.clone(), intercept))
+        .with_state(state)
+}
+
+#[derive(Serialize)]
+struct JsonErrorBody {
+    errors: [JsonErrorEntryBody; 1],
+}
+#[derive(Serialize)]
+struct JsonErrorEntryBody {
+    code: i32,
+    message: &'static str,
+}
+
+fn json_error(status: StatusCode, code: i32, message: &'static str) -> Response {
+    (
+        status,
+        Json(JsonErrorBody {
+            errors: [JsonErrorEntryBody { code, message }],
+        }),
+    )
+        .into_response()
+}
+
+/// Logs the request, then answers seeded failures and csrf handshakes
+async fn intercept(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let early = early_response(&mut state.lock().unwrap(), &request);
+    match early {
+        Some(response) => response,
+        None => next.run(request).await,
+    }
+}
+
+fn early_response(state: &mut MockState, request: &Request) -> Option<Response> {
+    let method = request.method();
+    state
+        .requests
+        .push(format!("{method} {}", request.uri().path()));
+    if let Some(failure) = state.failures.pop_front() {
+        let mut response = json_error(failure.status, 0, "mock failure");
+        if let Some(retry_after) = failure.retry_after {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.into());
+        }
+        return Some(response);
+    }
+    let has_csrf_token = request
+        .headers()
+        .get(CSRF_TOKEN_HEADER)
+        .is_some_and(|token| token.as_bytes() == state.csrf_token.as_bytes());
+    (state.require_csrf && method != Method::GET && !has_csrf_token).then(|| {
+        let mut response = json_error(StatusCode::FORBIDDEN, 0, "Token Validation Failed");
+        response
+            .headers_mut()
+            .insert(CSRF_TOKEN_HEADER, state.csrf_token.parse().unwrap());
+        response
+    })
+}
+
+fn is_authenticated(state: &MockState, headers: &HeaderMap) -> bool {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(".ROBLOSECURITY="))
+        .any(|value| {
+            state
+                .auth_cookie
+                .as_deref()
+                .is_none_or(|cookie| cookie == value)
+        })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PageBody<T> {
+    previous_page_cursor: Option<String>,
+    next_page_cursor: Option<String>,
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageQuery {
+    limit: Option<usize>,
+    cursor: Option<String>,
+    sort_order: Option<u8>,
+    #[serde(default)]
+    exclude_full_games: bool,
+}
+
+/// Cursors are the offset of the page
+fn page<T: Clone>(items: &[T], query: &PageQuery) -> PageBody<T> {
+    let limit = query.limit.unwrap_or(10);
+    let offset = query
+        .cursor
+        .as_deref()
+        .and_then(|cursor| cursor.parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(items.len());
+    let end = (offset + limit).min(items.len());
+    PageBody {
+        previous_page_cursor: (offset > 0).then(|| offset.saturating_sub(limit).to_string()),
+        next_page_cursor: (end < items.len()).then(|| end.to_string()),
+        data: items[offset..end].to_vec(),
+    }
+}
+
+async fn public_servers(
+    State(state): State<SharedState>,
+    Path((place, _)): Path<(u64, u8)>,
+    Query(query): Query<PageQuery>,
+) -> Response {
+    let mut servers = state
+        .lock()
+        .unwrap()
+        .servers
+        .get(&place)
+        .cloned()
+        .unwrap_or_default();
+    if query.exclude_full_games {
+        servers.retain(|server| server.playing < server.max_players);
+    }
+    servers.sort_by_key(|server| server.playing);
+    if query.sort_order != Some(1) {
+        servers.reverse();
+    }
+    Json(page(&servers, &query)).into_response()
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DetailedUser {
+    has_verified_badge: bool,
+    user_id: u64,
+    username: String,
+    display_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)]
+struct GroupInfoBody {
+    id: u64,
+    name: String,
+    description: String,
+    owner: Option<DetailedUser>,
+    shout: Option<()>,
+    member_count: usize,
+    is_builders_club_only: bool,
+    public_entry_allowed: bool,
+    has_verified_badge: bool,
+}
+
+async fn group_info(State(state): State<SharedState>, Path(group): Path<u64>) -> Response {
+    let body = group_info_body(&state.lock().unwrap(), group);
+    body.map_or_else(
+        || {
+            json_error(
+                StatusCode::BAD_REQUEST,
+                1,
+                "Group is invalid or does not exist.",
+            )
+        },
+        |body| Json(body).into_response(),
+    )
+}
+
+fn group_info_body(state: &MockState, group: u64) -> Option<GroupInfoBody> {
+    let group = state.groups.get(&group)?;
+    Some(GroupInfoBody {
+        id: group.id,
+        name: group.name.clone(),
+        description: group.description.clone(),
+        owner: group.owner.map(|owner| state.detailed_user(owner)),
+        shout: None,
+        member_count: group.members.len(),
+        is_builders_club_only: false,
+        public_entry_allowed: group.public_entry_allowed,
+        has_verified_badge: group.has_verified_badge,
+    })
+}
+
+#[derive(Serialize, Clone)]
+struct GroupMemberBody {
+    user: DetailedUser,
+    role: MockRole,
+}
+
+async fn group_members(
+    State(state): State<SharedState>,
+    Path(group): Path<u64>,
+    Query(query): Query<PageQuery>,
+) -> Response {
+    let members = group_member_bodies(&state.lock().unwrap(), group);
+    let Some(mut members) = members else {
+        return json_error(
+            StatusCode::BAD_REQUEST,
+            1,
+            "The group is invalid or does not exist.",
+        );
+    };
+    if query.sort_order == Some(2) {
+        members.reverse();
+    }
+    Json(page(&members, &query)).into_response()
+}
+
+fn group_member_bodies(state: &MockState, group: u64) -> Option<Vec<GroupMemberBody>> {
+    let group = state.groups.get(&group)?;
+    Some(
+        group
+            .members
+            .iter()
+            .map(|member| GroupMemberBody {
+                user: state.detailed_user(member.user_id),
+                role: member.role.clone(),
+            })
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupIdsQuery {
+    group_ids: String,
+}
+
+#[derive(Serialize)]
+struct BatchOwnerBody {
+    id: u64,
+    r#type: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchGroupBody {
+    id: u64,
+    name: String,
+    description: String,
+    owner: Option<BatchOwnerBody>,
+    created: DateTime<Utc>,
+    has_verified_badge: bool,
+}
+
+#[derive(Serialize)]
+struct DataBody<T> {
+    data: Vec<T>,
+}
+
+async fn group_info_batch(
+    State(state): State<SharedState>,
+    Query(query): Query<GroupIdsQuery>,
+) -> Response {
+    let state = state.lock().unwrap();
+    let data = query
+        .group_ids
+        .split(',')
+        .filter_map(|id| state.groups.get(&id.trim().parse().ok()?))
+        .map(|group| BatchGroupBody {
+            id: group.id,
+            name: group.name.clone(),
+            description: group.description.clone(),
+            owner: group.owner.map(|id| BatchOwnerBody { id, r#type: "User" }),
+            created: group.created,
+            has_verified_badge: group.has_verified_badge,
+        })
+        .collect();
+    drop(state);
+    Json(DataBody { data }).into_response()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsersByIdRequest {
+    user_ids: Vec<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserBody {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requested_username: Option<String>,
+    id: u64,
+    name: String,
+    display_name: String,
+    has_verified_badge: bool,
+}
+impl UserBody {
+    fn new(user: &MockUser, requested_username: Option<String>) -> Self {
+        Self {
+            requested_username,
+            id: user.id,
+            name: user.name.clone(),
+            display_name: user.display_name.clone(),
+            has_verified_badge: user.has_verified_badge,
+        }
+    }
+}
+
+async fn users_by_id(
+    State(state): State<SharedState>,
+    Json(request): Json<UsersByIdRequest>,
+) -> Response {
+    let state = state.lock().unwrap();
+    let data = request
+        .user_ids
+        .iter()
+        .filter_map(|id| state.users.get(id))
+        .map(|user| UserBody::new(user, None))
+        .collect();
+    drop(state);
+    Json(DataBody { data }).into_response()
+}
+
+#[derive(Deserialize)]
+struct UsersByUsernameRequest {
+    usernames: Vec<String>,
+}
+
+async fn users_by_username(
+    State(state): State<SharedState>,
+    Json(request): Json<UsersByUsernameRequest>,
+) -> Response {
+    let state = state.lock().unwrap();
+    let data = request
+        .usernames
+        .into_iter()
+        .filter_map(|username| {
+            let user = state
+                .users
+                .values()
+                .find(|user| user.name.eq_ignore_ascii_case(&username))?;
+            Some(UserBody::new(user, Some(username)))
+        })
+        .collect();
+    drop(state);
+    Json(DataBody { data }).into_response()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThumbnailRequest {
+    request_id: Option<String>,
+    target_id: Option<u64>,
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThumbnailBody {
+    request_id: Option<String>,
+    error_code: i32,
+    error_message: &'static str,
+    target_id: u64,
+    state: &'static str,
+    image_url: Option<String>,
+    version: Option<&'static str>,
+}
+
+async fn batch_thumbnails(
+    State(state): State<SharedState>,
+    Json(requests): Json<Vec<ThumbnailRequest>>,
+) -> Response {
+    let state = state.lock().unwrap();
+    let data = requests
+        .into_iter()
+        .map(|request| {
+            let target_id = request.target_id.unwrap_or_default();
+            let target = request
+                .token
+                .and_then(|token| state.player_tokens.get(&token).copied())
+                .unwrap_or(target_id);
+            let (state, image_url) = match state.thumbnails.get(&target) {
+                Some(MockThumbnail::Completed(url)) => ("Completed", Some(url.clone())),
+                Some(MockThumbnail::Pending) => ("Pending", None),
+                Some(MockThumbnail::Blocked) => ("Blocked", None),
+                None => ("Error", None),
+            };
+            ThumbnailBody {
+                request_id: request.request_id,
+                error_code: 0,
+                error_message: "",
+                target_id,
+                state,
+                version: image_url.is_some().then_some("TN3"),
+                image_url,
+            }
+        })
+        .collect();
+    drop(state);
+    Json(DataBody { data }).into_response()
+}
+
+#[derive(Serialize)]
+struct FundsBody {
+    robux: u64,
+}
+
+async fn group_funds(
+    State(state): State<SharedState>,
+    Path(group): Path<u64>,
+    headers: HeaderMap,
+) -> Response {
+    let state = state.lock().unwrap();
+    if !is_authenticated(&state, &headers) {
+        return json_error(
+            StatusCode::UNAUTHORIZED,
+            0,
+            "Authorization has been denied for this request.",
+        );
+    }
+    match state.group_funds.get(&group) {
+        Some(&robux) => Json(FundsBody { robux }).into_response(),
+        None => json_error(
+            StatusCode::BAD_REQUEST,
+            1,
+            "Group is invalid or does not exist.",
+        ),
+    }
+}
+
+/// Only reached with the csrf token, clients fetch the token by being refused here
+async fn logout() -> StatusCode {
+    StatusCode::OK
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use reqwest::ClientBuilder;
+use roblox_api::clients::{CookieClient, jar::JarFormat};
+
+fn client() -> CookieClient {
+    let client = CookieClient::new(ClientBuilder::new(), "auth");
+    client.insert_cookie("plain", "1");
+    client
+        .import_jar(
+            r#"{"scoped": {"value": "2", "domain": "www.roblox.com", "path": "/games", "expires": "2100-01-01T00:00:00Z", "secure": true}}"#,
+            JarFormat::Json,
+        )
+        .unwrap();
+    client
+}
+
+fn round_trip(format: JarFormat) {
+    let client = client();
+    let copy = CookieClient::new(ClientBuilder::new(), "other");
+    copy.import_jar(&client.export_jar(format), format).unwrap();
+    for name in [".ROBLOSECURITY", "plain", "scoped"] {
+        assert_eq!(
+            copy.get_stored_cookie(name),
+            client.get_stored_cookie(name),
+            "{name}"
+        );
+    }
+    assert_eq!(copy.get_auth_cookie().as_deref(), Some("auth"));
+}
+
+#[test]
+fn netscape_round_trip() {
+    round_trip(JarFormat::Netscape);
+}
+
+#[test]
+fn json_round_trip() {
+    round_trip(JarFormat::Json);
+}
+
+#[test]
+fn netscape_import() {
+    let client = CookieClient::new(ClientBuilder::new(), "auth");
+    client
+        .import_jar(
+            "# Netscape HTTP Cookie File\n\
+             #HttpOnly_.roblox.com\tTRUE\t/\tTRUE\t4102444800\t.ROBLOSECURITY\timported\n\
+             .roblox.com\tTRUE\t/\tFALSE\t1\texpired\tgone\n",
+            JarFormat::Netscape,
+        )
+        .unwrap();
+    let cookie = client.get_stored_cookie(".ROBLOSECURITY").unwrap();
+    assert_eq!(cookie.value, "imported");
+    assert!(cookie.secure);
+    assert_eq!(
+        cookie.expires,
+        Some(DateTime::<Utc>::from_timestamp(4_102_444_800, 0).unwrap())
+    );
+    assert_eq!(client.get_stored_cookie("expired"), None);
+}
@@ -0,0 +1,483 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use reqwest::StatusCode;
+use roblox_api::{
+    apis::{
+        Id, RequestLimit, SortOrder,
+        economy::EconomyAuthenticatedApi,
+        games::{GamesApi, ServerType},
+        groups::{GroupMember, GroupsApi},
+        pagination::{CursorStore, MemoryCursorStore},
+        thumbnails::{BatchRequest, BatchThumbnailResultExt, ThumbnailsApi},
+        users::UsersApi,
+    },
+    batcher::Batcher,
+    clients::{
+        ClientBuilder, CookieClient,
+        cache::CachingClient,
+        cassette::{Cassette, RecordingClient, ReplayClient},
+        proxy_pool::ProxyPool,
+    },
+    retry::RetryPolicy,
+    testing::{
+        MockFailure, MockGameServer, MockGroup, MockGroupMember, MockRoblox, MockRole, MockState,
+        MockThumbnail, MockUser,
+    },
+};
+use uuid::Uuid;
+
+fn id(id: u64) -> Id {
+    Id::new(id).unwrap()
+}
+
+fn requests(mock: &MockRoblox) -> Vec<String> {
+    std::mem::take(&mut mock.state().requests)
+}
+
+async fn start(state: MockState) -> MockRoblox {
+    MockRoblox::start(state.with_user(MockUser::new(1, "Builderman")))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn seeded_failure_is_answered_once() {
+    let mock = start(MockState::default()).await;
+    let client = mock.client();
+
+    mock.state().fail_next(MockFailure {
+        status: StatusCode::NOT_FOUND,
+        retry_after: None,
+    });
+    let result = client.get_user_info_from_id_batch([id(1)], false).await;
+    assert_eq!(result.unwrap_err().status(), Some(StatusCode::NOT_FOUND));
+    assert_eq!(requests(&mock), ["POST /users/v1/users"]);
+
+    let users = client.get_user_info_from_id_batch([id(1)], false).await;
+    assert_eq!(users.unwrap()[0].name, "Builderman");
+}
+
+fn group_with_members(count: u64) -> MockGroup {
+    let role = MockRole {
+        id: 1,
+        name: String::from("Member"),
+        rank: 1,
+    };
+    MockGroup {
+        members: (1..=count)
+            .map(|user_id| MockGroupMember {
+                user_id,
+                role: role.clone(),
+            })
+            .collect(),
+        ..MockGroup::new(7, "Group")
+    }
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_for_idempotent_requests_only() {
+    let mock = start(MockState::default().with_group(group_with_members(1))).await;
+    let client = mock
+        .cookie_client("cookie")
+        .with_retry_policy(RetryPolicy::new(3).with_base_delay(Duration::from_millis(1)));
+    client
+        .get_user_info_from_id_batch([id(1)], false)
+        .await
+        .unwrap();
+    requests(&mock);
+    let unavailable = MockFailure {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        retry_after: None,
+    };
+
+    mock.state().fail_next(unavailable);
+    assert_eq!(client.get_group_info(id(7)).await.unwrap().name, "Group");
+    assert_eq!(
+        requests(&mock),
+        ["GET /groups/v1/groups/7", "GET /groups/v1/groups/7"]
+    );
+
+    mock.state().fail_next(unavailable);
+    let result = client.get_user_info_from_id_batch([id(1)], false).await;
+    assert_eq!(
+        result.unwrap_err().status(),
+        Some(StatusCode::SERVICE_UNAVAILABLE)
+    );
+    assert_eq!(requests(&mock), ["POST /users/v1/users"]);
+}
+
+#[tokio::test]
+async fn rate_limits_are_retried_after_the_requested_delay() {
+    let mock = start(MockState::default()).await;
+    let client = mock
+        .cookie_client("cookie")
+        .with_retry_policy(RetryPolicy::new(2).with_base_delay(Duration::from_secs(10)));
+    client
+        .get_user_info_from_id_batch([id(1)], false)
+        .await
+        .unwrap();
+    requests(&mock);
+
+    mock.state().fail_next(MockFailure {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        retry_after: Some(0),
+    });
+    client
+        .get_user_info_from_id_batch([id(1)], false)
+        .await
+        .unwrap();
+    assert_eq!(
+        requests(&mock),
+        ["POST /users/v1/users", "POST /users/v1/users"]
+    );
+
+    mock.state().fail_next(MockFailure {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        retry_after: Some(0),
+    });
+    mock.state().fail_next(MockFailure {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        retry_after: Some(0),
+    });
+    let result = client.get_public_servers_manual(
+        id(1),
+        ServerType::Public,
+        SortOrder::Ascending,
+        false,
+        RequestLimit::Ten,
+        None::<String>,
+    );
+    assert!(result.await.unwrap_err().is_rate_limit());
+}
+
+#[tokio::test]
+async fn csrf_token_is_fetched_once_and_refreshed_on_rejection() {
+    let mock = start(MockState::default()).await;
+    let client = mock.cookie_client("cookie");
+
+    let users = client.get_user_info_from_id_batch([id(1)], false).await;
+    assert_eq!(users.unwrap()[0].name, "Builderman");
+    assert_eq!(
+        requests(&mock),
+        ["POST /auth/v2/logout", "POST /users/v1/users"]
+    );
+
+    mock.state().csrf_token = String::from("rotated");
+    client
+        .get_user_info_from_id_batch([id(1)], false)
+        .await
+        .unwrap();
+    assert_eq!(
+        requests(&mock),
+        ["POST /users/v1/users", "POST /users/v1/users"]
+    );
+}
+
+#[tokio::test]
+async fn unauthenticated_client_does_not_fetch_csrf_token() {
+    let mock = start(MockState::default()).await;
+    let client = mock.client();
+
+    client
+        .get_user_info_from_id_batch([id(1)], false)
+        .await
+        .unwrap();
+    assert_eq!(
+        requests(&mock),
+        ["POST /users/v1/users", "POST /users/v1/users"]
+    );
+}
+
+#[tokio::test]
+async fn forbidden_without_csrf_token_is_not_resent() {
+    let mock = start(MockState::default()).await;
+    let client = mock.cookie_client("cookie");
+    client
+        .get_user_info_from_id_batch([id(1)], false)
+        .await
+        .unwrap();
+    requests(&mock);
+
+    mock.state().fail_next(MockFailure {
+        status: StatusCode::FORBIDDEN,
+        retry_after: None,
+    });
+    let result = client.get_user_info_from_id_batch([id(1)], false).await;
+    assert_eq!(result.unwrap_err().status(), Some(StatusCode::FORBIDDEN));
+    assert_eq!(requests(&mock), ["POST /users/v1/users"]);
+}
+
+fn user_ids(members: &[GroupMember]) -> Vec<u64> {
+    members
+        .iter()
+        .map(|member| member.user.user_id.get())
+        .collect()
+}
+
+#[tokio::test]
+async fn paginated_items_resume_from_checkpoint() {
+    let mock = start(MockState::default().with_group(group_with_members(25))).await;
+    let client = mock.client();
+    let store: Arc<dyn CursorStore> = Arc::new(MemoryCursorStore::default());
+    let members = |limit| {
+        client
+            .get_group_members_resumable(
+                id(7),
+                RequestLimit::Ten,
+                SortOrder::Ascending,
+                store.clone(),
+                "members",
+            )
+            .limit(limit)
+    };
+
+    let first = members(15).map(Result::unwrap).collect::<Vec<_>>().await;
+    assert_eq!(user_ids(&first), (1..=15).collect::<Vec<_>>());
+    assert_eq!(store.load("members").as_deref(), Some("10"));
+
+    let rest = members(100).map(Result::unwrap).collect::<Vec<_>>().await;
+    assert_eq!(user_ids(&rest), (11..=25).collect::<Vec<_>>());
+    assert_eq!(store.load("members"), None);
+}
+
+#[tokio::test]
+async fn proxy_pool_of_cookie_clients_authenticates_each_proxy() {
+    let mut state = MockState::default().with_group_funds(7, 100);
+    state.auth_cookie = Some(String::from("cookie"));
+    let mock = start(state).await;
+    let pool =
+        ProxyPool::from_clients([mock.cookie_client("cookie"), mock.cookie_client("cookie")]);
+
+    assert_eq!(pool.get_group_funds(id(7)).await.unwrap(), 100);
+    for _ in 0..2 {
+        pool.get_user_info_from_id_batch([id(1)], false)
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        requests(&mock),
+        [
+            "GET /economy/v1/groups/7/currency",
+            "POST /auth/v2/logout",
+            "POST /users/v1/users",
+            "POST /auth/v2/logout",
+            "POST /users/v1/users"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn downloads_only_send_cookies_scoped_to_the_host() {
+    let mut state = MockState::default().with_group_funds(7, 100);
+    state.auth_cookie = Some(String::from("cookie"));
+    let mock = start(state).await;
+    let url = format!("http://{}/economy/v1/groups/7/currency", mock.address());
+
+    let scoped = mock.cookie_client("cookie").download(url.as_str()).await;
+    assert_eq!(&scoped.unwrap()[..], br#"{"robux":100}"#);
+
+    let roblox = CookieClient::new(ClientBuilder::new(), "cookie");
+    let unscoped = roblox.download(url.as_str()).await;
+    assert_eq!(
+        unscoped.unwrap_err().status(),
+        Some(StatusCode::UNAUTHORIZED)
+    );
+}
+
+#[tokio::test]
+async fn cached_responses_are_served_without_a_request() {
+    let mock = start(MockState::default().with_group(group_with_members(1))).await;
+    let client =
+        CachingClient::new(mock.client()).with_ttl("/v1/groups/*", Duration::from_secs(60));
+
+    for _ in 0..2 {
+        assert_eq!(client.get_group_info(id(7)).await.unwrap().name, "Group");
+    }
+    assert_eq!(requests(&mock), ["GET /groups/v1/groups/7"]);
+    assert_eq!(client.len(), 1);
+}
+
+/// More servers than fit in a page, the user's token only listed in the last one
+fn servers_with_user(user: u64) -> MockState {
+    let servers = (0..105)
+        .map(|index: u16| MockGameServer {
+            id: Uuid::from_u128(u128::from(index)),
+            max_players: 200,
+            playing: 200 - index,
+            player_tokens: vec![format!("token-{index}")],
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    let last = servers.len() - 1;
+    (0..last)
+        .fold(MockState::default(), |state, index| {
+            state.with_player_token(&format!("token-{index}"), 1000 + index as u64)
+        })
+        .with_player_token(&format!("token-{last}"), user)
+        .with_servers(10, servers)
+        .with_thumbnail(user, MockThumbnail::Completed(String::from("headshot-1")))
+        .with_thumbnail(2, MockThumbnail::Completed(String::from("headshot-2")))
+}
+
+#[tokio::test]
+async fn user_is_found_in_a_server_of_a_later_page() {
+    let mock = start(servers_with_user(1)).await;
+    let client = mock.client();
+
+    let server = client.find_user_in_servers(id(10), id(1)).await.unwrap();
+    assert_eq!(server, Some(Uuid::from_u128(104)));
+    let pages = requests(&mock)
+        .iter()
+        .filter(|request| request.starts_with("GET /games/"))
+        .count();
+    assert_eq!(pages, 2);
+}
+
+#[tokio::test]
+async fn user_in_no_server_is_not_found() {
+    let mock = start(servers_with_user(1)).await;
+    let client = mock.client();
+
+    assert_eq!(
+        client.find_user_in_servers(id(10), id(2)).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn user_without_headshot_is_not_searched_for() {
+    let mock = start(servers_with_user(1)).await;
+    let client = mock.client();
+
+    assert_eq!(
+        client.find_user_in_servers(id(10), id(3)).await.unwrap(),
+        None
+    );
+    assert!(
+        requests(&mock)
+            .iter()
+            .all(|request| !request.starts_with("GET /games/"))
+    );
+}
+
+fn groups_state() -> MockState {
+    MockState::default()
+        .with_group(MockGroup::new(7, "Seven"))
+        .with_group(MockGroup::new(8, "Eight"))
+}
+
+#[tokio::test]
+async fn batcher_coalesces_lookups_into_one_request() {
+    let mock = start(groups_state()).await;
+    let batcher = Batcher::groups(Arc::new(mock.client()));
+
+    let (seven, eight, again) =
+        tokio::join!(batcher.get(id(7)), batcher.get(id(8)), batcher.get(id(7)));
+    assert_eq!(seven.unwrap().unwrap().name, "Seven");
+    assert_eq!(eight.unwrap().unwrap().name, "Eight");
+    assert_eq!(again.unwrap().unwrap().name, "Seven");
+    assert_eq!(requests(&mock), ["GET /groups/v2/groups"]);
+}
+
+#[tokio::test]
+async fn batcher_shares_the_batch_error_with_every_lookup() {
+    let mock = start(groups_state()).await;
+    let batcher = Batcher::groups(Arc::new(mock.client()));
+    mock.state().fail_next(MockFailure {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        retry_after: None,
+    });
+
+    let (seven, eight, again) =
+        tokio::join!(batcher.get(id(7)), batcher.get(id(8)), batcher.get(id(7)));
+    let (seven, eight, again) = (seven.unwrap_err(), eight.unwrap_err(), again.unwrap_err());
+    assert_eq!(seven.status(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+    assert!(Arc::ptr_eq(&seven, &eight) && Arc::ptr_eq(&seven, &again));
+    assert_eq!(requests(&mock), ["GET /groups/v2/groups"]);
+}
+
+fn thumbnail_requests() -> [BatchRequest<&'static str, (), ()>; 2] {
+    [1, 2].map(|target| BatchRequest {
+        request_id: Some(if target == 1 { "pending" } else { "completed" }),
+        target_id: id(target).into(),
+        ..Default::default()
+    })
+}
+
+fn thumbnails_state() -> MockState {
+    MockState::default()
+        .with_thumbnail(1, MockThumbnail::Pending)
+        .with_thumbnail(2, MockThumbnail::Completed(String::from("two")))
+}
+
+#[tokio::test]
+async fn pending_thumbnails_are_polled_until_completed() {
+    let mock = start(thumbnails_state()).await;
+    let client = mock.client();
+
+    let (results, ()) = tokio::join!(
+        client.get_batch_thumbnails_resolved(thumbnail_requests(), Duration::from_secs(10)),
+        async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            mock.state()
+                .thumbnails
+                .insert(1, MockThumbnail::Completed(String::from("one")));
+        }
+    );
+    let results = results.unwrap();
+    assert_eq!(
+        results
+            .iter()
+            .map(|result| result.request_id())
+            .collect::<Vec<_>>(),
+        [Some("pending"), Some("completed")]
+    );
+    assert_eq!(results[0].as_ref().unwrap().image_url, "one");
+    assert_eq!(results[1].as_ref().unwrap().image_url, "two");
+}
+
+#[tokio::test]
+async fn pending_thumbnails_are_left_pending_at_the_timeout() {
+    let mock = start(thumbnails_state()).await;
+    let client = mock.client();
+
+    let results = client
+        .get_batch_thumbnails_resolved(thumbnail_requests(), Duration::from_millis(1500))
+        .await
+        .unwrap();
+    let pending = results[0].as_ref().unwrap_err();
+    assert_eq!(pending.request_id.as_deref(), Some("pending"));
+    assert!(pending.state.is_pending());
+    assert_eq!(results[1].as_ref().unwrap().image_url, "two");
+}
+
+#[tokio::test]
+async fn recorded_responses_are_replayed_without_the_server() {
+    let mock = start(groups_state()).await;
+    let recorder = RecordingClient::new(mock.client());
+    assert_eq!(recorder.get_group_info(id(7)).await.unwrap().name, "Seven");
+
+    let path = std::env::temp_dir().join(format!("cassette-{}.json", std::process::id()));
+    recorder.save(&path).unwrap();
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cassette, recorder.cassette());
+
+    let endpoints = mock.endpoints();
+    drop(mock);
+    let replay = ReplayClient::new(cassette).with_endpoints(endpoints);
+    assert_eq!(replay.get_group_info(id(7)).await.unwrap().name, "Seven");
+}
+
+#[tokio::test]
+async fn unrecorded_requests_are_not_found_on_replay() {
+    let mock = start(groups_state()).await;
+    let recorder = RecordingClient::new(mock.client());
+    recorder.get_group_info(id(7)).await.unwrap();
+
+    let replay = ReplayClient::new(recorder.cassette()).with_endpoints(mock.endpoints());
+    let result = replay.get_group_info(id(8)).await;
+    assert_eq!(result.unwrap_err().status(), Some(StatusCode::NOT_FOUND));
+    assert_eq!(requests(&mock), ["GET /groups/v1/groups/7"]);
+}
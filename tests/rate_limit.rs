@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use roblox_api::rate_limit::{Quota, RateLimiter};
+use tokio::time::Instant;
+
+const QUOTA: Quota = Quota::new("test", 2, Duration::from_secs(10));
+
+#[tokio::test(start_paused = true)]
+async fn acquire_waits_until_the_quota_refills() {
+    let limiter = RateLimiter::new();
+    let start = Instant::now();
+
+    limiter.acquire(&QUOTA, None).await;
+    limiter.acquire(&QUOTA, None).await;
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert!(limiter.try_acquire(&QUOTA, None).is_err());
+
+    limiter.acquire(&QUOTA, None).await;
+    let waited = start.elapsed();
+    assert!(
+        waited >= Duration::from_secs(5) && waited < Duration::from_millis(5100),
+        "{waited:?}"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn partitions_have_their_own_quota() {
+    let limiter = RateLimiter::new();
+
+    for _ in 0..2 {
+        assert!(limiter.try_acquire(&QUOTA, Some("first")).is_ok());
+    }
+    assert!(limiter.try_acquire(&QUOTA, Some("first")).is_err());
+    assert!(limiter.try_acquire(&QUOTA, Some("second")).is_ok());
+}
@@ -0,0 +1,21 @@
+use chrono::DateTime;
+use roblox_api::apis::two_step_verification::Totp;
+
+/// RFC 6238 appendix B, sha1 with the last 6 of the 8 digits
+#[test]
+fn rfc_6238_vectors() {
+    let totp = Totp::from_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+    for (time, code) in [
+        (59, "287082"),
+        (1_111_111_109, "081804"),
+        (1_111_111_111, "050471"),
+        (1_234_567_890, "005924"),
+        (2_000_000_000, "279037"),
+        (20_000_000_000, "353130"),
+    ] {
+        assert_eq!(
+            totp.code_at(DateTime::from_timestamp(time, 0).unwrap()),
+            code
+        );
+    }
+}